@@ -1,4 +1,5 @@
 use crate::spawning::LoadedAssets;
+use crate::{life_cycles, threat_boid};
 use bevy::audio::{PlaybackMode, Volume};
 use bevy::prelude::*;
 
@@ -49,6 +50,21 @@ pub fn system_update_active_audio_sources(
     active_audio_sources.0 = query.iter().count() as i32;
 }
 
+pub fn system_play_gameplay_sounds(
+    mut commands: Commands,
+    loaded_assets: Res<LoadedAssets>,
+    active_audio_sources: Res<ActiveAudioSources>,
+    mut duck_eaten_events: EventReader<threat_boid::DuckEaten>,
+    mut egg_hatched_events: EventReader<life_cycles::EggHatched>,
+) {
+    for _ in duck_eaten_events.read() {
+        play_duck_eaten(&loaded_assets, &mut commands, &active_audio_sources);
+    }
+    for _ in egg_hatched_events.read() {
+        play_egg_pop(&loaded_assets, &mut commands, &active_audio_sources);
+    }
+}
+
 pub fn play_egg_pop(
     loaded_assets: &Res<LoadedAssets>,
     commands: &mut Commands,
@@ -0,0 +1,294 @@
+// Runs the pond simulation without a window, renderer or audio and prints
+// the population over time as CSV, e.g.
+//
+//     cargo run --bin sim -- --seed 42 --duration 240 --food-policy flock --food-interval 2 --food-kind bread
+//
+// Food policies: `none`, `random` (anywhere in the world) and `flock` (near the flock center).
+// Food kinds: `bread`, `pretzel`, `croissant`, `cake` and `donut`.
+// Difficulties: `easy`, `normal`, `hard` and `custom`, from `--difficulty-presets`.
+
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use ducky_boids::boid::Boid;
+use ducky_boids::rng::SimulationRng;
+use ducky_boids::{
+    difficulty, director, food, game_state, genetics, life_cycles, lineage, movement, player, rng,
+    simulation, terrain, threat_boid, tuning,
+};
+use rand::Rng;
+
+#[derive(Clone, Copy)]
+enum FoodPolicy {
+    None,
+    Random,
+    Flock,
+}
+
+#[derive(Resource)]
+struct FoodScript {
+    policy: FoodPolicy,
+    kind: food::FoodKind,
+    timer: Timer,
+}
+
+struct SimArgs {
+    duration: f32,
+    sample_interval: f32,
+    food_policy: FoodPolicy,
+    food_kind: food::FoodKind,
+    food_interval: f32,
+    endless: bool,
+    tuning_path: String,
+    director_path: String,
+    map_path: String,
+    difficulty: difficulty::Difficulty,
+    difficulty_path: String,
+}
+
+fn parse_args() -> SimArgs {
+    let mut sim_args = SimArgs {
+        duration: 240.0,
+        sample_interval: 1.0,
+        food_policy: FoodPolicy::Flock,
+        food_kind: food::FoodKind::Bread,
+        food_interval: food::FoodKind::Bread.cooldown(),
+        endless: false,
+        tuning_path: format!("assets/{}", tuning::BOID_TUNING_PATH),
+        director_path: format!("assets/{}", director::THREAT_DIRECTOR_PATH),
+        map_path: format!("assets/{}", terrain::POND_MAP_PATH),
+        difficulty: difficulty::Difficulty::Normal,
+        difficulty_path: format!("assets/{}", difficulty::DIFFICULTY_PRESETS_PATH),
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--duration" => sim_args.duration = parse_value(&arg, args.next()),
+            "--sample-interval" => sim_args.sample_interval = parse_value(&arg, args.next()),
+            "--food-interval" => sim_args.food_interval = parse_value(&arg, args.next()),
+            "--food-policy" => {
+                sim_args.food_policy = match args.next().as_deref() {
+                    Some("none") => FoodPolicy::None,
+                    Some("random") => FoodPolicy::Random,
+                    Some("flock") => FoodPolicy::Flock,
+                    other => panic!("Unknown food policy {other:?}, expected none|random|flock"),
+                }
+            }
+            "--food-kind" => {
+                let name = args.next().unwrap_or_default();
+                sim_args.food_kind = food::FoodKind::ALL
+                    .into_iter()
+                    .find(|kind| kind.name().eq_ignore_ascii_case(&name))
+                    .unwrap_or_else(|| panic!("Unknown food kind {name:?}"));
+            }
+            "--endless" => sim_args.endless = true,
+            "--tuning" => sim_args.tuning_path = parse_value(&arg, args.next()),
+            "--director" => sim_args.director_path = parse_value(&arg, args.next()),
+            "--map" => sim_args.map_path = parse_value(&arg, args.next()),
+            "--difficulty" => sim_args.difficulty = parse_value(&arg, args.next()),
+            "--difficulty-presets" => sim_args.difficulty_path = parse_value(&arg, args.next()),
+            // Parsed by `rng::startup_seed`
+            "--seed" => {
+                args.next();
+            }
+            _ => panic!("Unknown argument {arg}"),
+        }
+    }
+    sim_args
+}
+
+fn parse_value<T: std::str::FromStr>(arg: &str, value: Option<String>) -> T {
+    value
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_else(|| panic!("Missing or invalid value for {arg}"))
+}
+
+fn main() {
+    let sim_args = parse_args();
+    let seed = rng::startup_seed();
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, StatesPlugin))
+        .add_plugins(simulation::SimulationPlugin { seed })
+        .insert_resource(TimeUpdateStrategy::ManualDuration(
+            Time::<Fixed>::default().timestep(),
+        ))
+        .insert_resource(FoodScript {
+            policy: sim_args.food_policy,
+            kind: sim_args.food_kind,
+            timer: Timer::from_seconds(sim_args.food_interval, TimerMode::Repeating),
+        })
+        // Ahead of the simulation chain, so its RNG draws always come first in a tick
+        .add_systems(
+            FixedUpdate,
+            system_scripted_food_placement
+                .before(director::system_threat_director)
+                .run_if(game_state::is_simulation_running),
+        );
+
+    match std::fs::read_to_string(&sim_args.tuning_path) {
+        Ok(tuning_file) => {
+            let boid_tuning: tuning::BoidTuning =
+                ron::de::from_str(&tuning_file).expect("Failed to parse the boid tuning file");
+            app.insert_resource(boid_tuning);
+        }
+        Err(error) => eprintln!(
+            "Could not read {}, using default tuning: {error}",
+            sim_args.tuning_path
+        ),
+    }
+
+    match std::fs::read_to_string(&sim_args.director_path) {
+        Ok(director_file) => {
+            let director_config: director::ThreatDirectorConfig = ron::de::from_str(&director_file)
+                .expect("Failed to parse the threat director file");
+            app.insert_resource(director_config);
+        }
+        Err(error) => eprintln!(
+            "Could not read {}, using default threat director: {error}",
+            sim_args.director_path
+        ),
+    }
+
+    match std::fs::read_to_string(&sim_args.map_path) {
+        Ok(map_file) => {
+            let pond_map: terrain::PondMap =
+                ron::de::from_str(&map_file).expect("Failed to parse the pond map file");
+            app.insert_resource(pond_map);
+        }
+        Err(error) => eprintln!(
+            "Could not read {}, using an open pond: {error}",
+            sim_args.map_path
+        ),
+    }
+
+    let difficulty_presets = match std::fs::read_to_string(&sim_args.difficulty_path) {
+        Ok(difficulty_file) => ron::de::from_str(&difficulty_file)
+            .expect("Failed to parse the difficulty presets file"),
+        Err(error) => {
+            eprintln!(
+                "Could not read {}, using default difficulty presets: {error}",
+                sim_args.difficulty_path
+            );
+            difficulty::DifficultyPresets::default()
+        }
+    };
+    app.world_mut()
+        .resource_mut::<difficulty::CurrentDifficulty>()
+        .select(sim_args.difficulty, &difficulty_presets);
+    app.insert_resource(difficulty_presets);
+    // Read up front, nothing swaps in partway through a run
+    app.insert_resource(simulation::LoadedConfigs::all());
+
+    app.finish();
+    app.cleanup();
+
+    // The first update spawns the initial flock, then the round starts like after pressing "Play"
+    app.update();
+    app.world_mut()
+        .resource_mut::<player::PlayerStats>()
+        .is_simulating = sim_args.endless;
+    app.world_mut()
+        .resource_mut::<NextState<game_state::GameState>>()
+        .set(game_state::GameState::Running);
+
+    println!(
+        "time,eggs,ducklings,juveniles,adults,threats,ducks_born,score,remaining_time,\
+         speed,size,fertility,threat_awareness,generation,dynasties"
+    );
+    eprintln!("seed: {seed}");
+
+    let mut next_sample_time = 0.0;
+    loop {
+        app.update();
+
+        let elapsed = app.world().resource::<Time<Fixed>>().elapsed_seconds();
+        let state = *app.world().resource::<State<game_state::GameState>>().get();
+        let finished = elapsed >= sim_args.duration || state != game_state::GameState::Running;
+
+        if elapsed >= next_sample_time || finished {
+            print_sample(app.world_mut(), elapsed);
+            next_sample_time += sim_args.sample_interval;
+        }
+
+        if finished {
+            eprintln!("finished after {elapsed:.2}s in state {state:?}");
+            break;
+        }
+    }
+}
+
+fn count<F: bevy::ecs::query::QueryFilter>(world: &mut World) -> usize {
+    world.query_filtered::<(), F>().iter(world).count()
+}
+
+fn print_sample(world: &mut World, elapsed: f32) {
+    let eggs = count::<With<life_cycles::Egg>>(world);
+    let ducklings = count::<With<life_cycles::Duckling>>(world);
+    let juveniles = count::<With<life_cycles::Juvenile>>(world);
+    let adults = count::<With<life_cycles::Adult>>(world);
+    let threats = count::<With<threat_boid::Threat>>(world);
+
+    // Flock averages, to watch it evolve
+    let mut genome_query = world.query::<&genetics::Genome>();
+    let genome_count = genome_query.iter(world).count().max(1) as f32;
+    let genome_sum = genome_query
+        .iter(world)
+        .fold([0.0; 4], |sum, genome| {
+            [
+                sum[0] + genome.speed,
+                sum[1] + genome.size,
+                sum[2] + genome.fertility,
+                sum[3] + genome.threat_awareness,
+            ]
+        })
+        .map(|sum| sum / genome_count);
+
+    let family_tree = world.resource::<lineage::FamilyTree>();
+    let player_stats = world.resource::<player::PlayerStats>();
+    println!(
+        "{elapsed:.2},{eggs},{ducklings},{juveniles},{adults},{threats},{},{},{:.2},{:.3},{:.3},{:.3},{:.3},{},{}",
+        player_stats.ducks_born,
+        player_stats.score,
+        player_stats.remaining_time,
+        genome_sum[0],
+        genome_sum[1],
+        genome_sum[2],
+        genome_sum[3],
+        family_tree.generation_reached(),
+        family_tree.dynasties()
+    );
+}
+
+fn system_scripted_food_placement(
+    time: Res<Time>,
+    world_bounds: Res<movement::WorldBounds>,
+    mut food_script: ResMut<FoodScript>,
+    mut food_placement_queue: ResMut<food::FoodPlacementQueue>,
+    mut rng: ResMut<SimulationRng>,
+    duck_query: Query<&Transform, With<Boid>>,
+) {
+    if !food_script.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let random_offset = Vec2::new(rng.gen::<f32>() - 0.5, rng.gen::<f32>() - 0.5);
+    let position = match food_script.policy {
+        FoodPolicy::None => return,
+        FoodPolicy::Random => random_offset * (world_bounds.size - Vec2::splat(200.0)),
+        FoodPolicy::Flock => {
+            let duck_count = duck_query.iter().count();
+            if duck_count == 0 {
+                return;
+            }
+            let flock_center = duck_query.iter().fold(Vec2::ZERO, |acc, transform| {
+                acc + transform.translation.xy()
+            }) / duck_count as f32;
+            flock_center + random_offset * 200.0
+        }
+    };
+
+    // Spawned by `food::system_apply_food_placements` like the player's food
+    food_placement_queue.0.push((position, food_script.kind));
+}
@@ -1,70 +1,77 @@
 use crate::movement::Velocity;
+use crate::spatial_grid::SpatialGrid;
 use bevy::prelude::*;
 use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
 
 #[derive(Component)]
 pub struct Boid;
 
-const PROTECTED_RADIUS_2: f32 = 70.0 * 70.0;
-const AVOID_FACTOR: f32 = 0.35;
+// Per-entity flocking weights, swapped out by the life cycle transitions
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+pub struct BoidProfile {
+    pub protected_radius: f32,
+    pub avoid_factor: f32,
+    pub visibility_radius: f32,
+    pub align_factor: f32,
+    pub cohesion_factor: f32,
+}
+
 pub fn system_boid_separation(
     time: Res<Time>,
-    transform_query: Query<(Entity, &Transform), With<Boid>>,
-    mut velocity_query: Query<(Entity, &mut Velocity), With<Boid>>,
+    spatial_grid: Res<SpatialGrid>,
+    mut query: Query<(Entity, &Transform, &BoidProfile, &mut Velocity), With<Boid>>,
 ) {
-    for (entity, transform) in transform_query.iter() {
+    for (entity, transform, boid_profile, mut velocity) in query.iter_mut() {
+        let position = transform.translation.xy();
         let mut avoid_vector: Vec2 = Vec2::ZERO;
 
-        for (other_entity, other_transform) in transform_query.iter() {
-            if entity == other_entity {
-                continue;
-            }
-            if (transform.translation.xy() - other_transform.translation.xy()).length_squared()
-                > PROTECTED_RADIUS_2
-            {
-                continue;
-            }
+        spatial_grid.for_each_in_radius(
+            position,
+            boid_profile.protected_radius,
+            |other_entity, other_position, _| {
+                if entity == other_entity {
+                    return;
+                }
 
-            avoid_vector += transform.translation.xy() - other_transform.translation.xy();
-        }
+                avoid_vector += position - other_position;
+            },
+        );
 
-        velocity_query.get_mut(entity).unwrap().1 .0 +=
-            avoid_vector * AVOID_FACTOR * time.delta_seconds();
+        velocity.0 += avoid_vector * boid_profile.avoid_factor * time.delta_seconds();
     }
 }
 
-pub const VISIBILITY_RADIUS_2: f32 = 150.0 * 150.0;
-const ALIGN_FACTOR: f32 = 0.5;
-const COHESION_FACTOR: f32 = 0.15;
 pub fn system_boid_alignment_and_cohesion(
     time: Res<Time>,
+    spatial_grid: Res<SpatialGrid>,
     mut queries: ParamSet<(
-        Query<(Entity, &Velocity, &Transform), With<Boid>>,
-        Query<(Entity, &mut Velocity, &Transform), With<Boid>>,
+        Query<(Entity, &Velocity, &Transform, &BoidProfile), With<Boid>>,
+        Query<(Entity, &mut Velocity, &Transform, &BoidProfile), With<Boid>>,
     )>,
 ) {
     let mut velocity_and_pos_avg_map: HashMap<Entity, (Vec2, Vec2)> = Default::default();
 
     let query = queries.p0();
-    for (entity, _, transform) in query.iter() {
+    for (entity, _, transform, boid_profile) in query.iter() {
         let mut velocity_average: Vec2 = Vec2::ZERO;
         let mut position_average: Vec2 = Vec2::ZERO;
         let mut neighbors: i32 = 0;
 
-        for (other_entity, other_velocity, other_transform) in query.iter() {
-            if entity == other_entity {
-                continue;
-            }
-            if (transform.translation.xy() - other_transform.translation.xy()).length_squared()
-                > VISIBILITY_RADIUS_2
-            {
-                continue;
-            }
-
-            velocity_average += other_velocity.0;
-            position_average += other_transform.translation.xy();
-            neighbors += 1;
-        }
+        spatial_grid.for_each_in_radius(
+            transform.translation.xy(),
+            boid_profile.visibility_radius,
+            |other_entity, other_position, _| {
+                if entity == other_entity {
+                    return;
+                }
+                if let Ok((_, other_velocity, _, _)) = query.get(other_entity) {
+                    velocity_average += other_velocity.0;
+                    position_average += other_position;
+                    neighbors += 1;
+                }
+            },
+        );
 
         if neighbors == 0 {
             continue;
@@ -74,11 +81,11 @@ pub fn system_boid_alignment_and_cohesion(
         velocity_and_pos_avg_map.insert(entity, (velocity_average, position_average));
     }
 
-    for (entity, mut velocity, transform) in queries.p1().iter_mut() {
+    for (entity, mut velocity, transform, boid_profile) in queries.p1().iter_mut() {
         if let Some((velocity_addition, position_average)) = velocity_and_pos_avg_map.get(&entity) {
-            velocity.0 += *velocity_addition * ALIGN_FACTOR * time.delta_seconds();
+            velocity.0 += *velocity_addition * boid_profile.align_factor * time.delta_seconds();
             velocity.0 += (*position_average - transform.translation.xy())
-                * COHESION_FACTOR
+                * boid_profile.cohesion_factor
                 * time.delta_seconds();
         }
     }
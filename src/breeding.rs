@@ -1,5 +1,11 @@
+use crate::difficulty::CurrentDifficulty;
 use crate::duck_boid::CloseAdults;
-use crate::life_cycles::Adult;
+use crate::food::FoodBoost;
+use crate::genetics::Genome;
+use crate::hunger::Hunger;
+use crate::life_cycles::{Adult, Elder};
+use crate::lineage::{Ancestry, FamilyTree};
+use crate::rng::SimulationRng;
 use crate::{game_state, spawning};
 use bevy::prelude::*;
 use bevy::utils::HashSet;
@@ -138,19 +144,65 @@ pub fn system_update_relationships(
 #[derive(Component, Default)]
 pub struct BreedingProgress(pub f32);
 
-pub const BREEDING_DURATION: f32 = 4.0 * crate::life_cycles::TIME_FACTOR;
+pub const BREEDING_DURATION: f32 = 4.0;
+// Breeding slows down by this for each elder partner
+const ELDER_BREEDING_FACTOR: f32 = 0.5;
 
 pub fn system_breeding(
     time: Res<Time>,
     loaded_assets: Res<spawning::LoadedAssets>,
     mut commands: Commands,
-    mut relationship_query: Query<(&Transform, &mut BreedingProgress), With<Relationship>>,
+    mut relationship_query: Query<(&Transform, &Relationship, &mut BreedingProgress)>,
+    hunger_query: Query<&Hunger>,
+    elder_query: Query<(), With<Elder>>,
+    food_boost_query: Query<&FoodBoost>,
+    partner_query: Query<(&Genome, &Ancestry)>,
+    mut family_tree: ResMut<FamilyTree>,
+    current_difficulty: Res<CurrentDifficulty>,
+    mut rng: ResMut<SimulationRng>,
 ) {
-    for (transform, mut breeding_progress) in relationship_query.iter_mut() {
-        breeding_progress.0 += time.delta_seconds();
+    for (transform, relationship, mut breeding_progress) in relationship_query.iter_mut() {
+        // Only fed partners breed, the progress waits for both of them to eat
+        let partners_fed = [relationship.partner1, relationship.partner2]
+            .iter()
+            .all(|partner| hunger_query.get(*partner).is_ok_and(Hunger::is_fed));
+        if !partners_fed {
+            continue;
+        }
 
-        if breeding_progress.0 >= BREEDING_DURATION {
-            spawning::spawn_boid(transform.translation.xy(), &mut commands, &loaded_assets);
+        let elder_partners = [relationship.partner1, relationship.partner2]
+            .iter()
+            .filter(|partner| elder_query.contains(**partner))
+            .count();
+        // A boost from either partner is enough
+        let boost_factor = [relationship.partner1, relationship.partner2]
+            .iter()
+            .filter_map(|partner| food_boost_query.get(*partner).ok())
+            .map(FoodBoost::breeding_factor)
+            .fold(1.0, f32::max);
+        let ((genome1, ancestry1), (genome2, ancestry2)) = if let (Ok(partner1), Ok(partner2)) = (
+            partner_query.get(relationship.partner1),
+            partner_query.get(relationship.partner2),
+        ) {
+            (partner1, partner2)
+        } else {
+            continue;
+        };
+        let fertility = (genome1.fertility + genome2.fertility) * 0.5;
+        breeding_progress.0 += time.delta_seconds()
+            * ELDER_BREEDING_FACTOR.powi(elder_partners as i32)
+            * boost_factor
+            * fertility;
+
+        if breeding_progress.0 >= BREEDING_DURATION * current_difficulty.settings.time_factor {
+            spawning::spawn_boid(
+                transform.translation.xy(),
+                Genome::inherit(genome1, genome2, &mut rng),
+                family_tree.add_offspring(ancestry1, ancestry2),
+                &mut commands,
+                &loaded_assets,
+                &mut rng,
+            );
 
             breeding_progress.0 = 0.0;
         }
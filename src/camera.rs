@@ -0,0 +1,147 @@
+use crate::boid::Boid;
+use crate::movement::WorldBounds;
+use crate::threat_boid::Threat;
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+// Middle mouse drag pans, the scroll wheel zooms and F toggles following the flock
+#[derive(Component)]
+pub struct CameraController {
+    pub follow_flock: bool,
+    // Cursor position of the last frame while dragging
+    drag_position: Option<Vec2>,
+}
+
+impl Default for CameraController {
+    fn default() -> Self {
+        Self {
+            follow_flock: true,
+            drag_position: None,
+        }
+    }
+}
+
+const MIN_ZOOM: f32 = 0.5;
+// Zoom factor per scroll line
+const ZOOM_STEP: f32 = 1.1;
+// Scroll pixels that count as one line, for touchpads
+const PIXELS_PER_SCROLL_LINE: f32 = 100.0;
+// How quickly the camera catches up with the flock
+const FOLLOW_SPEED: f32 = 2.0;
+
+pub fn system_camera_input(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mouse_button_input: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut mouse_wheel_events: EventReader<MouseWheel>,
+    mut camera_query: Query<(
+        &mut CameraController,
+        &mut Transform,
+        &mut OrthographicProjection,
+    )>,
+) {
+    let (mut camera_controller, mut transform, mut projection) =
+        if let Ok(camera) = camera_query.get_single_mut() {
+            camera
+        } else {
+            return;
+        };
+
+    if keyboard_input.just_pressed(KeyCode::KeyF) {
+        camera_controller.follow_flock = !camera_controller.follow_flock;
+    }
+
+    for event in mouse_wheel_events.read() {
+        let lines = match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_SCROLL_LINE,
+        };
+        // Scrolling up zooms in
+        projection.scale *= ZOOM_STEP.powf(-lines);
+    }
+
+    let cursor_position = window_query
+        .get_single()
+        .ok()
+        .and_then(Window::cursor_position);
+    if !mouse_button_input.pressed(MouseButton::Middle) || cursor_position.is_none() {
+        camera_controller.drag_position = None;
+        return;
+    }
+
+    let cursor_position = cursor_position.unwrap();
+    if let Some(drag_position) = camera_controller.drag_position {
+        // The cursor's y axis points down, the world's up
+        let delta = cursor_position - drag_position;
+        transform.translation.x -= delta.x * projection.scale;
+        transform.translation.y += delta.y * projection.scale;
+        camera_controller.follow_flock = false;
+    }
+    camera_controller.drag_position = Some(cursor_position);
+}
+
+pub fn system_follow_flock(
+    time: Res<Time>,
+    mut camera_query: Query<(&CameraController, &mut Transform)>,
+    duck_query: Query<&Transform, (With<Boid>, Without<Threat>, Without<CameraController>)>,
+) {
+    let (camera_controller, mut transform) = if let Ok(camera) = camera_query.get_single_mut() {
+        camera
+    } else {
+        return;
+    };
+
+    let duck_count = duck_query.iter().count();
+    if !camera_controller.follow_flock || duck_count == 0 {
+        return;
+    }
+
+    let flock_center = duck_query.iter().fold(Vec2::ZERO, |acc, transform| {
+        acc + transform.translation.xy()
+    }) / duck_count as f32;
+    let blend = 1.0 - (-FOLLOW_SPEED * time.delta_seconds()).exp();
+    let translation = transform.translation.xy().lerp(flock_center, blend);
+    transform.translation.x = translation.x;
+    transform.translation.y = translation.y;
+}
+
+// Keeps the view inside the world, zoomed out no further than the whole world
+pub fn system_clamp_camera_to_world(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    world_bounds: Res<WorldBounds>,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), With<CameraController>>,
+) {
+    let window = if let Ok(window) = window_query.get_single() {
+        window
+    } else {
+        return;
+    };
+
+    let (mut transform, mut projection) = if let Ok(camera) = camera_query.get_single_mut() {
+        camera
+    } else {
+        return;
+    };
+
+    let window_size = window.size();
+    if window_size.min_element() <= 0.0 {
+        return;
+    }
+
+    let max_zoom = (world_bounds.size / window_size)
+        .max_element()
+        .max(MIN_ZOOM);
+    let scale = projection.scale.clamp(MIN_ZOOM, max_zoom);
+    if projection.scale != scale {
+        projection.scale = scale;
+    }
+
+    // Centered on an axis where the view is wider than the world
+    let max_offset = (world_bounds.size - window_size * scale).max(Vec2::ZERO) * 0.5;
+    let translation = transform.translation.xy().clamp(-max_offset, max_offset);
+    if transform.translation.xy() != translation {
+        transform.translation.x = translation.x;
+        transform.translation.y = translation.y;
+    }
+}
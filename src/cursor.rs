@@ -1,5 +1,5 @@
-use crate::food;
 use crate::spawning::LoadedAssets;
+use crate::{food, scare};
 use bevy::prelude::*;
 
 #[derive(Component)]
@@ -88,6 +88,8 @@ pub fn system_update_game_cursor_image(
     mut cursor: Query<&mut UiImage, With<GameCursor>>,
     loaded_assets: Res<LoadedAssets>,
     food_placement_timer: Res<food::FoodPlacementTimer>,
+    selected_food_kind: Res<food::SelectedFoodKind>,
+    scare_timer: Res<scare::ScareTimer>,
 ) {
     let mut cursor_image = if let Ok(cursor) = cursor.get_single_mut() {
         cursor
@@ -95,11 +97,15 @@ pub fn system_update_game_cursor_image(
         return;
     };
 
-    if food_placement_timer.0.finished() {
-        if cursor_image.texture != loaded_assets.cursor_food_sprite {
-            cursor_image.texture = loaded_assets.cursor_food_sprite.clone();
-        }
-    } else if cursor_image.texture != loaded_assets.cursor_empty_sprite {
-        cursor_image.texture = loaded_assets.cursor_empty_sprite.clone();
+    let texture = if scare_timer.just_scared() {
+        &loaded_assets.cursor_scare_sprite
+    } else if food_placement_timer.is_ready(selected_food_kind.0) {
+        &loaded_assets.cursor_food_sprite
+    } else {
+        &loaded_assets.cursor_empty_sprite
+    };
+
+    if cursor_image.texture != *texture {
+        cursor_image.texture = texture.clone();
     }
 }
@@ -0,0 +1,299 @@
+use crate::movement;
+use crate::simulation::LoadedConfigs;
+use crate::threat_boid::Threat;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoadFailedEvent, AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub const DIFFICULTY_PRESETS_PATH: &str = "config/presets.difficulty.ron";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash, Debug, Default)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    // Starts out as set up in the presets file, and can be changed in the main menu
+    Custom,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Custom,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+            Difficulty::Custom => "Custom",
+        }
+    }
+
+    // The one after it in the main menu selector
+    pub fn next(self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Custom,
+            Difficulty::Custom => Difficulty::Easy,
+        }
+    }
+}
+
+// By name, ignoring case, like `--difficulty hard` for the `sim` binary
+impl std::str::FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Difficulty::ALL
+            .into_iter()
+            .find(|difficulty| difficulty.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("Unknown difficulty {name:?}"))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct DifficultySettings {
+    // Seconds a round lasts
+    pub round_length: f32,
+    // Scales the placement cooldown of every food kind
+    pub food_cooldown_factor: f32,
+    // Scales how long ducks take to hatch, grow up, breed and grow old
+    pub time_factor: f32,
+    // Scales how many threats the director sends, in the background and in waves
+    pub threat_spawn_factor: f32,
+    // Scales how fast threats can run
+    pub threat_speed_factor: f32,
+}
+
+impl Default for DifficultySettings {
+    fn default() -> Self {
+        Self {
+            round_length: 240.0,
+            food_cooldown_factor: 1.0,
+            time_factor: 1.0,
+            threat_spawn_factor: 1.0,
+            threat_speed_factor: 1.0,
+        }
+    }
+}
+
+// The settings of `Difficulty::Custom` that can be changed in the main menu
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum CustomSetting {
+    RoundLength,
+    FoodCooldown,
+    Time,
+    ThreatSpawns,
+    ThreatSpeed,
+}
+
+impl CustomSetting {
+    pub const ALL: [CustomSetting; 5] = [
+        CustomSetting::RoundLength,
+        CustomSetting::FoodCooldown,
+        CustomSetting::Time,
+        CustomSetting::ThreatSpawns,
+        CustomSetting::ThreatSpeed,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CustomSetting::RoundLength => "Round Length",
+            CustomSetting::FoodCooldown => "Food Cooldown",
+            CustomSetting::Time => "Duck Aging",
+            CustomSetting::ThreatSpawns => "Threat Spawns",
+            CustomSetting::ThreatSpeed => "Threat Speed",
+        }
+    }
+
+    // How much one press of the - or + button changes it
+    fn step(self) -> f32 {
+        match self {
+            CustomSetting::RoundLength => 30.0,
+            _ => 0.1,
+        }
+    }
+
+    fn range(self) -> (f32, f32) {
+        match self {
+            CustomSetting::RoundLength => (60.0, 600.0),
+            CustomSetting::ThreatSpeed => (0.5, 1.5),
+            _ => (0.2, 3.0),
+        }
+    }
+
+    fn value_mut(self, settings: &mut DifficultySettings) -> &mut f32 {
+        match self {
+            CustomSetting::RoundLength => &mut settings.round_length,
+            CustomSetting::FoodCooldown => &mut settings.food_cooldown_factor,
+            CustomSetting::Time => &mut settings.time_factor,
+            CustomSetting::ThreatSpawns => &mut settings.threat_spawn_factor,
+            CustomSetting::ThreatSpeed => &mut settings.threat_speed_factor,
+        }
+    }
+
+    // Moves the setting by a number of steps, snapped to the step so repeated presses don't drift
+    pub fn adjust(self, settings: &mut DifficultySettings, steps: f32) {
+        let step = self.step();
+        let (min, max) = self.range();
+        let value = self.value_mut(settings);
+        *value = ((*value / step).round() * step + steps * step).clamp(min, max);
+    }
+
+    pub fn format(self, settings: &DifficultySettings) -> String {
+        match self {
+            CustomSetting::RoundLength => format!("{:.0}s", settings.round_length),
+            CustomSetting::FoodCooldown => format!("x{:.1}", settings.food_cooldown_factor),
+            CustomSetting::Time => format!("x{:.1}", settings.time_factor),
+            CustomSetting::ThreatSpawns => format!("x{:.1}", settings.threat_spawn_factor),
+            CustomSetting::ThreatSpeed => format!("x{:.1}", settings.threat_speed_factor),
+        }
+    }
+}
+
+// Settings for every difficulty, loaded from `DIFFICULTY_PRESETS_PATH`
+#[derive(Asset, TypePath, Resource, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct DifficultyPresets {
+    pub easy: DifficultySettings,
+    pub normal: DifficultySettings,
+    pub hard: DifficultySettings,
+    pub custom: DifficultySettings,
+}
+
+impl Default for DifficultyPresets {
+    fn default() -> Self {
+        Self {
+            easy: DifficultySettings {
+                round_length: 300.0,
+                food_cooldown_factor: 0.75,
+                time_factor: 0.8,
+                threat_spawn_factor: 0.7,
+                threat_speed_factor: 0.85,
+            },
+            normal: DifficultySettings::default(),
+            hard: DifficultySettings {
+                round_length: 180.0,
+                food_cooldown_factor: 1.25,
+                time_factor: 1.2,
+                threat_spawn_factor: 1.4,
+                threat_speed_factor: 1.15,
+            },
+            custom: DifficultySettings::default(),
+        }
+    }
+}
+
+impl DifficultyPresets {
+    pub fn settings(&self, difficulty: Difficulty) -> &DifficultySettings {
+        match difficulty {
+            Difficulty::Easy => &self.easy,
+            Difficulty::Normal => &self.normal,
+            Difficulty::Hard => &self.hard,
+            Difficulty::Custom => &self.custom,
+        }
+    }
+}
+
+// The difficulty the round is played on, with its settings
+#[derive(Resource, Default)]
+pub struct CurrentDifficulty {
+    pub difficulty: Difficulty,
+    pub settings: DifficultySettings,
+}
+
+impl CurrentDifficulty {
+    pub fn select(&mut self, difficulty: Difficulty, presets: &DifficultyPresets) {
+        self.difficulty = difficulty;
+        self.settings = presets.settings(difficulty).clone();
+    }
+}
+
+#[derive(Default)]
+pub struct DifficultyPresetsLoader;
+
+impl AssetLoader for DifficultyPresetsLoader {
+    type Asset = DifficultyPresets;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes::<DifficultyPresets>(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["difficulty.ron"]
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct DifficultyPresetsHandle(pub Handle<DifficultyPresets>);
+
+pub fn system_load_difficulty_presets(
+    asset_server: Res<AssetServer>,
+    mut presets_handle: ResMut<DifficultyPresetsHandle>,
+) {
+    presets_handle.0 = asset_server.load(DIFFICULTY_PRESETS_PATH);
+}
+
+// Copies the asset into the `DifficultyPresets` resource whenever it is (re)loaded
+pub fn system_apply_difficulty_presets(
+    mut asset_events: EventReader<AssetEvent<DifficultyPresets>>,
+    mut load_failed_events: EventReader<AssetLoadFailedEvent<DifficultyPresets>>,
+    presets_assets: Res<Assets<DifficultyPresets>>,
+    presets_handle: Res<DifficultyPresetsHandle>,
+    mut presets: ResMut<DifficultyPresets>,
+    mut current_difficulty: ResMut<CurrentDifficulty>,
+    mut loaded_configs: ResMut<LoadedConfigs>,
+) {
+    for event in load_failed_events.read() {
+        warn!(
+            "Could not load {}, using the default presets: {}",
+            event.path, event.error
+        );
+        loaded_configs.difficulty_presets = true;
+    }
+
+    for event in asset_events.read() {
+        match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => {
+                if *id != presets_handle.0.id() {
+                    continue;
+                }
+                if let Some(loaded_presets) = presets_assets.get(*id) {
+                    *presets = loaded_presets.clone();
+                    let difficulty = current_difficulty.difficulty;
+                    current_difficulty.select(difficulty, &presets);
+                    loaded_configs.difficulty_presets = true;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// Threats already around when the difficulty got picked, like the one waiting on the main menu
+pub fn system_apply_threat_speed(
+    current_difficulty: Res<CurrentDifficulty>,
+    mut threat_query: Query<(&Threat, &mut movement::VelocityLimits)>,
+) {
+    for (threat, mut velocity_limits) in threat_query.iter_mut() {
+        velocity_limits.max =
+            threat.kind.max_speed() * current_difficulty.settings.threat_speed_factor;
+    }
+}
@@ -0,0 +1,473 @@
+use crate::boid::Boid;
+use crate::difficulty::CurrentDifficulty;
+use crate::rng::SimulationRng;
+use crate::simulation::LoadedConfigs;
+use crate::spawning::{CurrentThreats, LoadedAssets};
+use crate::terrain::PondMap;
+use crate::threat_boid::{Threat, ThreatKind};
+use crate::{movement, spawning};
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoadFailedEvent, AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+pub const THREAT_DIRECTOR_PATH: &str = "config/threats.director.ron";
+
+// Sent `warning_time` seconds before a wave comes in, at the edge it comes in from
+#[derive(Event)]
+pub struct ThreatWaveWarning {
+    pub position: Vec2,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ThreatWave {
+    // Seconds into the round
+    pub start: f32,
+    pub count: u32,
+    // Cycled through for the threats of the wave
+    pub kinds: Vec<ThreatKind>,
+    // Seconds between two threats of the wave
+    #[serde(default)]
+    pub interval: f32,
+    // No background spawning for this long after the last threat of the wave came in
+    #[serde(default)]
+    pub quiet_after: f32,
+}
+
+// When and how many threats come in over a round, loaded from `THREAT_DIRECTOR_PATH`
+#[derive(Asset, TypePath, Resource, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ThreatDirectorConfig {
+    // (seconds into the round, threats per duck), linear in between and held after the last one
+    pub pressure_curve: Vec<(f32, f32)>,
+    pub max_background_threats: i32,
+    // Weights for picking the kind of a background threat
+    pub background_kinds: Vec<(ThreatKind, f32)>,
+
+    pub warning_time: f32,
+    pub waves: Vec<ThreatWave>,
+    // Endless rounds start over with the first wave after this many seconds
+    pub cycle_length: f32,
+}
+
+impl Default for ThreatDirectorConfig {
+    fn default() -> Self {
+        Self {
+            pressure_curve: vec![(0.0, 0.1), (120.0, 0.1), (240.0, 0.12)],
+            max_background_threats: 4,
+            background_kinds: vec![
+                (ThreatKind::Cat, 0.5),
+                (ThreatKind::Fox, 0.2),
+                (ThreatKind::Hawk, 0.15),
+                (ThreatKind::Pike, 0.15),
+            ],
+
+            warning_time: 4.0,
+            waves: vec![
+                ThreatWave {
+                    start: 60.0,
+                    count: 1,
+                    kinds: vec![ThreatKind::Cat],
+                    interval: 1.0,
+                    quiet_after: 15.0,
+                },
+                ThreatWave {
+                    start: 120.0,
+                    count: 2,
+                    kinds: vec![ThreatKind::Cat, ThreatKind::Fox],
+                    interval: 1.5,
+                    quiet_after: 20.0,
+                },
+                ThreatWave {
+                    start: 180.0,
+                    count: 2,
+                    kinds: vec![ThreatKind::Hawk, ThreatKind::Pike],
+                    interval: 1.0,
+                    quiet_after: 15.0,
+                },
+                ThreatWave {
+                    start: 220.0,
+                    count: 3,
+                    kinds: vec![ThreatKind::Cat, ThreatKind::Fox, ThreatKind::Hawk],
+                    interval: 1.0,
+                    quiet_after: 20.0,
+                },
+            ],
+            cycle_length: 240.0,
+        }
+    }
+}
+
+impl ThreatDirectorConfig {
+    pub fn pressure(&self, round_time: f32) -> f32 {
+        let mut previous: Option<(f32, f32)> = None;
+        for &(time, pressure) in self.pressure_curve.iter() {
+            if round_time < time {
+                return if let Some((previous_time, previous_pressure)) = previous {
+                    let t = (round_time - previous_time) / (time - previous_time);
+                    previous_pressure + (pressure - previous_pressure) * t
+                } else {
+                    pressure
+                };
+            }
+            previous = Some((time, pressure));
+        }
+        previous.map_or(0.0, |(_, pressure)| pressure)
+    }
+
+    fn random_background_kind(&self, rng: &mut SimulationRng) -> ThreatKind {
+        let total_weight: f32 = self.background_kinds.iter().map(|(_, weight)| weight).sum();
+        let mut roll = rng.gen::<f32>() * total_weight;
+        for &(kind, weight) in self.background_kinds.iter() {
+            if roll < weight {
+                return kind;
+            }
+            roll -= weight;
+        }
+        ThreatKind::Cat
+    }
+}
+
+// A threat of a wave that is on its way, saved with the round so the rest of the wave still comes
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct IncomingThreat {
+    time: f32,
+    kind: ThreatKind,
+    angle: f32,
+}
+
+// Where the director is in the current round
+#[derive(Resource, Default)]
+pub struct ThreatDirector {
+    pub round_time: f32,
+    cycle_start: f32,
+    next_wave: usize,
+    // Set once the next wave got announced
+    wave_angle: Option<f32>,
+    incoming: Vec<IncomingThreat>,
+    quiet_until: f32,
+}
+
+impl ThreatDirector {
+    // Picks up a round that was saved `round_time` seconds in, skipping the waves that already
+    // came in. A wave that was only announced gets announced again.
+    pub fn resume_at(
+        round_time: f32,
+        incoming: Vec<IncomingThreat>,
+        config: &ThreatDirectorConfig,
+    ) -> Self {
+        let cycle_start = if config.cycle_length > 0.0 {
+            (round_time / config.cycle_length).floor() * config.cycle_length
+        } else {
+            0.0
+        };
+        let next_wave = config
+            .waves
+            .iter()
+            .take_while(|wave| cycle_start + wave.start <= round_time)
+            .count();
+        Self {
+            round_time,
+            cycle_start,
+            next_wave,
+            incoming,
+            ..Default::default()
+        }
+    }
+
+    pub fn incoming(&self) -> &[IncomingThreat] {
+        &self.incoming
+    }
+
+    pub fn is_wave_incoming(&self) -> bool {
+        self.wave_angle.is_some()
+    }
+
+    pub fn is_quiet(&self) -> bool {
+        self.incoming.is_empty() && self.round_time < self.quiet_until
+    }
+}
+
+#[derive(Default)]
+pub struct ThreatDirectorConfigLoader;
+
+impl AssetLoader for ThreatDirectorConfigLoader {
+    type Asset = ThreatDirectorConfig;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes::<ThreatDirectorConfig>(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["director.ron"]
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ThreatDirectorConfigHandle(pub Handle<ThreatDirectorConfig>);
+
+pub fn system_load_threat_director_config(
+    asset_server: Res<AssetServer>,
+    mut config_handle: ResMut<ThreatDirectorConfigHandle>,
+) {
+    config_handle.0 = asset_server.load(THREAT_DIRECTOR_PATH);
+}
+
+// Copies the asset into the `ThreatDirectorConfig` resource whenever it is (re)loaded
+pub fn system_apply_threat_director_config(
+    mut asset_events: EventReader<AssetEvent<ThreatDirectorConfig>>,
+    mut load_failed_events: EventReader<AssetLoadFailedEvent<ThreatDirectorConfig>>,
+    config_assets: Res<Assets<ThreatDirectorConfig>>,
+    config_handle: Res<ThreatDirectorConfigHandle>,
+    mut config: ResMut<ThreatDirectorConfig>,
+    mut loaded_configs: ResMut<LoadedConfigs>,
+) {
+    for event in load_failed_events.read() {
+        warn!(
+            "Could not load {}, using the default threat director: {}",
+            event.path, event.error
+        );
+        loaded_configs.threat_director = true;
+    }
+
+    for event in asset_events.read() {
+        match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => {
+                if *id != config_handle.0.id() {
+                    continue;
+                }
+                if let Some(loaded_config) = config_assets.get(*id) {
+                    *config = loaded_config.clone();
+                    loaded_configs.threat_director = true;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn system_threat_director(
+    time: Res<Time>,
+    mut commands: Commands,
+    loaded_assets: Res<LoadedAssets>,
+    config: Res<ThreatDirectorConfig>,
+    current_difficulty: Res<CurrentDifficulty>,
+    mut director: ResMut<ThreatDirector>,
+    current_threats: Res<CurrentThreats>,
+    world_bounds: Res<movement::WorldBounds>,
+    pond_map: Res<PondMap>,
+    duck_query: Query<Entity, (With<Boid>, Without<Threat>)>,
+    mut rng: ResMut<SimulationRng>,
+    mut wave_warning_events: EventWriter<ThreatWaveWarning>,
+) {
+    director.round_time += time.delta_seconds();
+    let round_time = director.round_time;
+    let threat_spawn_factor = current_difficulty.settings.threat_spawn_factor;
+
+    if director.next_wave >= config.waves.len()
+        && config.cycle_length > 0.0
+        && round_time >= director.cycle_start + config.cycle_length
+    {
+        director.cycle_start += config.cycle_length;
+        director.next_wave = 0;
+    }
+
+    if let Some(wave) = config.waves.get(director.next_wave) {
+        let wave_start = director.cycle_start + wave.start;
+
+        if director.wave_angle.is_none() && round_time >= wave_start - config.warning_time {
+            let angle = rng.gen::<f32>() * TAU;
+            director.wave_angle = Some(angle);
+
+            // Where that direction crosses the world edge
+            let direction = Vec2::from_angle(angle);
+            let edge_distance = (world_bounds.size * 0.5 / direction.abs()).min_element();
+            wave_warning_events.send(ThreatWaveWarning {
+                position: direction * edge_distance,
+            });
+        }
+
+        if round_time >= wave_start {
+            let angle = director
+                .wave_angle
+                .take()
+                .unwrap_or_else(|| rng.gen::<f32>() * TAU);
+            let count = (wave.count as f32 * threat_spawn_factor).round().max(1.0) as u32;
+            for index in 0..count {
+                let kind = wave
+                    .kinds
+                    .get(index as usize % wave.kinds.len().max(1))
+                    .copied()
+                    .unwrap_or_default();
+                director.incoming.push(IncomingThreat {
+                    time: wave_start + index as f32 * wave.interval,
+                    kind,
+                    angle,
+                });
+            }
+            director.quiet_until =
+                wave_start + count.saturating_sub(1) as f32 * wave.interval + wave.quiet_after;
+            director.next_wave += 1;
+        }
+    }
+
+    let mut index = 0;
+    while index < director.incoming.len() {
+        if director.incoming[index].time > round_time {
+            index += 1;
+            continue;
+        }
+        let incoming = director.incoming.remove(index);
+        // Spread out a little so a wave doesn't come in single file
+        let angle = incoming.angle + (rng.gen::<f32>() - 0.5) * 0.5;
+        let position = spawning::threat_spawn_position(
+            incoming.kind,
+            angle,
+            world_bounds.size,
+            &pond_map,
+            &mut rng,
+        );
+        spawning::spawn_threat(
+            position,
+            incoming.kind,
+            &mut commands,
+            &loaded_assets,
+            &current_difficulty.settings,
+            &mut rng,
+            false,
+        );
+    }
+
+    if !director.incoming.is_empty() || director.is_quiet() {
+        return;
+    }
+
+    let duck_count = duck_query.iter().count();
+    let wanted_threats = (config.pressure(round_time) * threat_spawn_factor * duck_count as f32)
+        .min(config.max_background_threats as f32 * threat_spawn_factor);
+    if (current_threats.0 as f32) < wanted_threats {
+        let kind = config.random_background_kind(&mut rng);
+        let angle = rng.gen::<f32>() * TAU;
+        let position =
+            spawning::threat_spawn_position(kind, angle, world_bounds.size, &pond_map, &mut rng);
+        spawning::spawn_threat(
+            position,
+            kind,
+            &mut commands,
+            &loaded_assets,
+            &current_difficulty.settings,
+            &mut rng,
+            false,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with_curve(pressure_curve: Vec<(f32, f32)>) -> ThreatDirectorConfig {
+        ThreatDirectorConfig {
+            pressure_curve,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn pressure_interpolates_between_points() {
+        let config = config_with_curve(vec![(10.0, 0.2), (20.0, 0.4)]);
+        assert!((config.pressure(15.0) - 0.3).abs() < 1e-6);
+        assert!((config.pressure(12.5) - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn pressure_is_clamped_to_the_ends_of_the_curve() {
+        let config = config_with_curve(vec![(10.0, 0.2), (20.0, 0.4)]);
+        assert_eq!(config.pressure(0.0), 0.2);
+        assert_eq!(config.pressure(20.0), 0.4);
+        assert_eq!(config.pressure(1000.0), 0.4);
+    }
+
+    #[test]
+    fn pressure_without_a_curve_is_zero() {
+        let config = config_with_curve(Vec::new());
+        assert_eq!(config.pressure(30.0), 0.0);
+    }
+
+    #[test]
+    fn resume_skips_the_waves_that_came_in() {
+        // Waves at 60, 120, 180 and 220 with a 4 second warning
+        let config = ThreatDirectorConfig::default();
+
+        let director = ThreatDirector::resume_at(100.0, Vec::new(), &config);
+        assert_eq!(director.cycle_start, 0.0);
+        assert_eq!(director.next_wave, 1);
+
+        let director = ThreatDirector::resume_at(120.0, Vec::new(), &config);
+        assert_eq!(director.next_wave, 2);
+
+        let director = ThreatDirector::resume_at(30.0, Vec::new(), &config);
+        assert_eq!(director.next_wave, 0);
+    }
+
+    #[test]
+    fn resume_keeps_an_announced_wave() {
+        let config = ThreatDirectorConfig::default();
+
+        // The 120 s wave was announced at 116 s but hasn't come in yet
+        let director = ThreatDirector::resume_at(117.0, Vec::new(), &config);
+        assert_eq!(director.next_wave, 1);
+        assert!(!director.is_wave_incoming());
+    }
+
+    #[test]
+    fn resume_keeps_the_incoming_threats() {
+        let config = ThreatDirectorConfig::default();
+        let incoming = vec![IncomingThreat {
+            time: 125.0,
+            kind: ThreatKind::Fox,
+            angle: 1.0,
+        }];
+
+        let director = ThreatDirector::resume_at(122.0, incoming, &config);
+        assert_eq!(director.incoming().len(), 1);
+        assert!(!director.is_quiet());
+    }
+
+    #[test]
+    fn resume_starts_at_the_current_cycle() {
+        let config = ThreatDirectorConfig::default();
+
+        let director = ThreatDirector::resume_at(250.0, Vec::new(), &config);
+        assert_eq!(director.cycle_start, 240.0);
+        assert_eq!(director.next_wave, 0);
+        assert_eq!(director.round_time, 250.0);
+
+        let director = ThreatDirector::resume_at(540.0, Vec::new(), &config);
+        assert_eq!(director.cycle_start, 480.0);
+        assert_eq!(director.next_wave, 1);
+    }
+
+    #[test]
+    fn resume_without_cycles_stays_in_the_first_one() {
+        let config = ThreatDirectorConfig {
+            cycle_length: 0.0,
+            ..Default::default()
+        };
+
+        let director = ThreatDirector::resume_at(500.0, Vec::new(), &config);
+        assert_eq!(director.cycle_start, 0.0);
+        assert_eq!(director.next_wave, config.waves.len());
+    }
+}
@@ -1,14 +1,24 @@
 use crate::boid::Boid;
+use crate::genetics::Genome;
+use crate::hunger::Hunger;
 use crate::movement::Velocity;
-use crate::{food, life_cycles, spawning, threat_boid, vfx};
+use crate::spatial_grid::SpatialGrid;
+use crate::tuning::BoidTuning;
+use crate::{food, life_cycles, threat_boid};
 use bevy::prelude::*;
-use bevy::utils::HashMap;
+use bevy::utils::{HashMap, HashSet};
+
+#[derive(Event)]
+pub struct FoodEaten {
+    pub position: Vec2,
+}
 
 #[derive(Component, Default)]
 pub struct CloseAdults(pub Vec<(Vec2, Entity)>);
 
-const CLOSE_ADULTS_RADIUS_2: f32 = 75.0 * 75.0;
+const CLOSE_ADULTS_RADIUS: f32 = 75.0;
 pub fn system_boid_update_close_adults(
+    spatial_grid: Res<SpatialGrid>,
     mut queries: ParamSet<(
         Query<(Entity, &Transform), (With<Boid>, With<life_cycles::Adult>)>,
         Query<(Entity, &mut CloseAdults), (With<Boid>, With<life_cycles::Adult>)>,
@@ -20,16 +30,16 @@ pub fn system_boid_update_close_adults(
     for (entity, transform) in query.iter() {
         let mut close_adults_array: Vec<(Vec2, Entity)> = Vec::with_capacity(10);
 
-        for (other_entity, other_transform) in query.iter() {
-            if entity == other_entity {
-                continue;
-            }
-            if (transform.translation.xy() - other_transform.translation.xy()).length_squared()
-                < CLOSE_ADULTS_RADIUS_2
-            {
-                close_adults_array.push((other_transform.translation.xy(), other_entity));
-            }
-        }
+        spatial_grid.for_each_in_radius(
+            transform.translation.xy(),
+            CLOSE_ADULTS_RADIUS,
+            |other_entity, other_position, _| {
+                if entity == other_entity || !query.contains(other_entity) {
+                    return;
+                }
+                close_adults_array.push((other_position, other_entity));
+            },
+        );
         close_adults_map.insert(entity, close_adults_array);
     }
 
@@ -41,7 +51,7 @@ pub fn system_boid_update_close_adults(
     }
 }
 
-const MATING_VISIBILITY_RADIUS_2: f32 = 500.0 * 500.0;
+const MATING_VISIBILITY_RADIUS: f32 = 500.0;
 const MATING_MIN_DISTANCE_2: f32 = 50.0 * 50.0;
 const TOWARDS_LONELY_ADULT_FACTOR: f32 = 50.0;
 const AVOID_ADULT_GROUP_FACTOR: f32 = 0.4;
@@ -54,6 +64,7 @@ struct CloseAdultsMapEntry {
 
 pub fn system_boid_mating_attraction(
     time: Res<Time>,
+    spatial_grid: Res<SpatialGrid>,
     mut queries: ParamSet<(
         Query<(Entity, &Transform, &CloseAdults), (With<Boid>, With<life_cycles::Adult>)>,
         Query<(Entity, &mut Velocity, &Transform), (With<Boid>, With<life_cycles::Adult>)>,
@@ -66,45 +77,45 @@ pub fn system_boid_mating_attraction(
         let mut biggest_adult_group: Option<(Vec2, i32)> = None; // (avg position, number of adults)
         let mut closest_lonely_adult_position: Option<(Vec2, f32)> = None; // (position, distance squared)
 
-        for (other_entity, other_transform, other_close_adults) in query.iter() {
-            if entity == other_entity {
-                continue;
-            }
-            if (transform.translation.xy() - other_transform.translation.xy()).length_squared()
-                > MATING_VISIBILITY_RADIUS_2
-            {
-                continue;
-            }
-
-            if other_close_adults.0.is_empty()
-                || (other_close_adults.0.len() == 1 && other_close_adults.0[0].1 == entity)
-            {
-                let distance_squared = (transform.translation.xy()
-                    - other_transform.translation.xy())
-                .length_squared();
+        spatial_grid.for_each_in_radius(
+            transform.translation.xy(),
+            MATING_VISIBILITY_RADIUS,
+            |other_entity, other_position, distance_squared| {
+                if entity == other_entity {
+                    return;
+                }
+                let other_close_adults = if let Ok((_, _, close_adults)) = query.get(other_entity) {
+                    close_adults
+                } else {
+                    return;
+                };
 
-                if closest_lonely_adult_position.is_none()
-                    || distance_squared < closest_lonely_adult_position.unwrap().1
+                let other_is_lonely = other_close_adults.0.is_empty()
+                    || (other_close_adults.0.len() == 1 && other_close_adults.0[0].1 == entity);
+                if other_is_lonely
+                    && closest_lonely_adult_position
+                        .is_none_or(|(_, closest_distance_2)| distance_squared < closest_distance_2)
                 {
-                    closest_lonely_adult_position =
-                        Some((other_transform.translation.xy(), distance_squared));
+                    closest_lonely_adult_position = Some((other_position, distance_squared));
                 }
-            }
 
-            if closest_lonely_adult_position.is_some() {
-                continue;
-            }
+                if closest_lonely_adult_position.is_some() {
+                    return;
+                }
 
-            let close_adults_numb = other_close_adults.0.len() as i32;
-            if biggest_adult_group.is_none() || close_adults_numb > biggest_adult_group.unwrap().1 {
-                let avg_position = other_close_adults
-                    .0
-                    .iter()
-                    .fold(Vec2::ZERO, |acc, entry| acc + entry.0)
-                    / close_adults_numb as f32;
-                biggest_adult_group = Some((avg_position, close_adults_numb));
-            }
-        }
+                let close_adults_numb = other_close_adults.0.len() as i32;
+                if biggest_adult_group
+                    .is_none_or(|(_, biggest_numb)| close_adults_numb > biggest_numb)
+                {
+                    let avg_position = other_close_adults
+                        .0
+                        .iter()
+                        .fold(Vec2::ZERO, |acc, entry| acc + entry.0)
+                        / close_adults_numb as f32;
+                    biggest_adult_group = Some((avg_position, close_adults_numb));
+                }
+            },
+        );
 
         close_adults_map.insert(
             entity,
@@ -117,23 +128,19 @@ pub fn system_boid_mating_attraction(
 
     let mut mut_query = queries.p1();
     for (entity, mut velocity, transform) in mut_query.iter_mut() {
-        let entry = close_adults_map.get(&entity);
-        if entry.is_none() {
+        let entry = if let Some(entry) = close_adults_map.get(&entity) {
+            entry
+        } else {
             continue;
-        }
-        let entry = entry.unwrap();
+        };
 
-        if entry.closest_lonely_adult_position.is_some() {
-            let distance_2 = entry.closest_lonely_adult_position.unwrap().1;
+        if let Some((lonely_adult_position, distance_2)) = entry.closest_lonely_adult_position {
             if distance_2 > MATING_MIN_DISTANCE_2 {
-                let direction = (entry.closest_lonely_adult_position.unwrap().0
-                    - transform.translation.xy())
-                .normalize();
+                let direction = (lonely_adult_position - transform.translation.xy()).normalize();
                 velocity.0 += direction * TOWARDS_LONELY_ADULT_FACTOR * time.delta_seconds();
             }
-        } else if entry.biggest_adult_group.is_some() {
-            let direction =
-                (entry.biggest_adult_group.unwrap().0 - transform.translation.xy()).normalize();
+        } else if let Some((adult_group_position, _)) = entry.biggest_adult_group {
+            let direction = (adult_group_position - transform.translation.xy()).normalize();
             velocity.0 -= direction * AVOID_ADULT_GROUP_FACTOR * time.delta_seconds();
         }
     }
@@ -141,105 +148,172 @@ pub fn system_boid_mating_attraction(
 
 const FOOD_VISIBILITY_RADIUS_2: f32 = 200.0 * 200.0;
 const FOOD_EATING_RADIUS_2: f32 = 25.0 * 25.0;
-const TOWARDS_FOOD_FACTOR: f32 = 120.0;
 pub fn system_boids_food(
     time: Res<Time>,
+    boid_tuning: Res<BoidTuning>,
     mut commands: Commands,
-    loaded_assets: Res<spawning::LoadedAssets>,
-    food_query: Query<(Entity, &Transform), With<food::Food>>,
-    mut duck_query: Query<(&Transform, &mut Velocity), With<Boid>>,
+    food_query: Query<(Entity, &Transform, &food::Food)>,
+    mut duck_query: Query<(Entity, &Transform, &mut Velocity, Option<&mut Hunger>), With<Boid>>,
+    mut food_eaten_events: EventWriter<FoodEaten>,
 ) {
-    for (duck_transform, mut duck_velocity) in duck_query.iter_mut() {
-        let mut closest_food: Option<(Vec2, f32, Entity)> = None;
-        for (entity, food_transform) in food_query.iter() {
+    // Food is only despawned at the end of the system, so it can't be eaten twice in the meantime
+    let mut eaten_food: HashSet<Entity> = Default::default();
+    for (duck_entity, duck_transform, mut duck_velocity, hunger) in duck_query.iter_mut() {
+        let mut closest_food: Option<(Vec2, f32, Entity, &food::Food)> = None;
+        for (entity, food_transform, food) in food_query.iter() {
+            if eaten_food.contains(&entity) {
+                continue;
+            }
             let distance_2 = (duck_transform.translation.xy() - food_transform.translation.xy())
                 .length_squared();
             if distance_2 < FOOD_VISIBILITY_RADIUS_2
-                && (closest_food.is_none() || distance_2 < closest_food.unwrap().1)
+                && closest_food
+                    .is_none_or(|(_, closest_distance_2, _, _)| distance_2 < closest_distance_2)
             {
-                closest_food = Some((food_transform.translation.xy(), distance_2, entity));
+                closest_food = Some((food_transform.translation.xy(), distance_2, entity, food));
             }
         }
 
-        if closest_food.is_some() {
-            let closet_food_distance_2 = closest_food.unwrap().1;
+        if let Some((food_position, closet_food_distance_2, food_entity, food)) = closest_food {
             if closet_food_distance_2 <= FOOD_EATING_RADIUS_2 {
-                let mut despawned: bool = false;
-                if let Some(mut entity_cmd) = commands.get_entity(closest_food.unwrap().2) {
+                if let Some(mut entity_cmd) = commands.get_entity(food_entity) {
                     entity_cmd.despawn();
-                    despawned = true;
-                }
-                if despawned {
-                    vfx::spawn_food_eaten_effect(
-                        &mut commands,
-                        &loaded_assets,
-                        closest_food.unwrap().0,
-                    );
+                    eaten_food.insert(food_entity);
+                    food_eaten_events.send(FoodEaten {
+                        position: food_position,
+                    });
+                    if let Some(mut hunger) = hunger {
+                        hunger.eat(food.nutrition());
+                    }
+                    if let Some(food_boost) = food.boost() {
+                        commands.entity(duck_entity).try_insert(food_boost);
+                    }
                 }
 
                 continue;
             }
 
-            let direction = (closest_food.unwrap().0 - duck_transform.translation.xy()).normalize();
-            duck_velocity.0 += direction * TOWARDS_FOOD_FACTOR * time.delta_seconds();
+            let direction = (food_position - duck_transform.translation.xy()).normalize();
+            duck_velocity.0 += direction * boid_tuning.towards_food_factor * time.delta_seconds();
         }
     }
 }
 
-const TOWARDS_ADULT_RADIUS_2: f32 = 200.0 * 200.0;
+const TOWARDS_ADULT_RADIUS: f32 = 200.0;
 const TOWARDS_ADULT_FACTOR: f32 = 50.0;
 pub fn system_boids_ducklings_towards_adults(
     time: Res<Time>,
+    spatial_grid: Res<SpatialGrid>,
     mut duckling_query: Query<
         (&Transform, &mut Velocity),
         (With<life_cycles::Duckling>, Without<life_cycles::Adult>),
     >,
-    adult_query: Query<&Transform, (With<life_cycles::Adult>, Without<life_cycles::Duckling>)>,
+    adult_query: Query<(), (With<life_cycles::Adult>, Without<life_cycles::Duckling>)>,
 ) {
     for (duckling_transform, mut duckling_velocity) in duckling_query.iter_mut() {
         let mut closest_adult: Option<(Vec2, f32)> = None;
-        for adult_transform in adult_query.iter() {
-            let distance_2 = (duckling_transform.translation.xy()
-                - adult_transform.translation.xy())
-            .length_squared();
-            if distance_2 < TOWARDS_ADULT_RADIUS_2
-                && (closest_adult.is_none() || distance_2 < closest_adult.unwrap().1)
+        spatial_grid.for_each_in_radius(
+            duckling_transform.translation.xy(),
+            TOWARDS_ADULT_RADIUS,
+            |adult_entity, adult_position, distance_2| {
+                if !adult_query.contains(adult_entity) {
+                    return;
+                }
+                if closest_adult
+                    .is_none_or(|(_, closest_distance_2)| distance_2 < closest_distance_2)
+                {
+                    closest_adult = Some((adult_position, distance_2));
+                }
+            },
+        );
+
+        if let Some((adult_position, _)) = closest_adult {
+            let direction = (adult_position - duckling_transform.translation.xy()).normalize();
+            duckling_velocity.0 += direction * TOWARDS_ADULT_FACTOR * time.delta_seconds();
+        }
+    }
+}
+
+const MOB_TRIGGER_RADIUS_2: f32 = 150.0 * 150.0;
+const MOB_JOIN_RADIUS_2: f32 = 250.0 * 250.0;
+// Adults close in on threats that come near ducklings or eggs
+pub fn system_adults_mob_threats(
+    time: Res<Time>,
+    boid_tuning: Res<BoidTuning>,
+    threat_query: Query<&Transform, With<threat_boid::Threat>>,
+    young_query: Query<&Transform, Or<(With<life_cycles::Duckling>, With<life_cycles::Egg>)>>,
+    mut adult_query: Query<
+        (&Transform, &mut Velocity),
+        (With<life_cycles::Adult>, Without<threat_boid::Threat>),
+    >,
+) {
+    let threatening_positions: Vec<Vec2> = threat_query
+        .iter()
+        .map(|threat_transform| threat_transform.translation.xy())
+        .filter(|threat_position| {
+            young_query.iter().any(|young_transform| {
+                young_transform
+                    .translation
+                    .xy()
+                    .distance_squared(*threat_position)
+                    <= MOB_TRIGGER_RADIUS_2
+            })
+        })
+        .collect();
+    if threatening_positions.is_empty() {
+        return;
+    }
+
+    for (adult_transform, mut adult_velocity) in adult_query.iter_mut() {
+        let adult_position = adult_transform.translation.xy();
+        let mut closest_threat: Option<(Vec2, f32)> = None;
+        for threat_position in threatening_positions.iter() {
+            let distance_2 = adult_position.distance_squared(*threat_position);
+            if distance_2 < MOB_JOIN_RADIUS_2
+                && closest_threat
+                    .is_none_or(|(_, closest_distance_2)| distance_2 < closest_distance_2)
             {
-                closest_adult = Some((adult_transform.translation.xy(), distance_2));
+                closest_threat = Some((*threat_position, distance_2));
             }
         }
 
-        if closest_adult.is_some() {
-            let direction =
-                (closest_adult.unwrap().0 - duckling_transform.translation.xy()).normalize();
-            duckling_velocity.0 += direction * TOWARDS_ADULT_FACTOR * time.delta_seconds();
+        if let Some((threat_position, _)) = closest_threat {
+            let direction = (threat_position - adult_position).normalize_or_zero();
+            adult_velocity.0 += direction * boid_tuning.mob_threats_factor * time.delta_seconds();
         }
     }
 }
 
 const AVOID_THREATS_RADIUS_2: f32 = 200.0 * 200.0;
-const AVOID_THREATS_FACTOR: f32 = 100.0;
 pub fn system_boids_avoid_threat(
     time: Res<Time>,
-    mut duck_query: Query<(&Transform, &mut Velocity), (With<Boid>, Without<threat_boid::Threat>)>,
+    boid_tuning: Res<BoidTuning>,
+    mut duck_query: Query<
+        (&Transform, &mut Velocity, Option<&Genome>),
+        (With<Boid>, Without<threat_boid::Threat>),
+    >,
     threat_query: Query<&Transform, With<threat_boid::Threat>>,
 ) {
-    for (duck_transform, mut duck_velocity) in duck_query.iter_mut() {
+    for (duck_transform, mut duck_velocity, genome) in duck_query.iter_mut() {
+        let threat_awareness = genome.map_or(1.0, |genome| genome.threat_awareness);
         let mut closest_threat: Option<(Vec2, f32)> = None;
         for threat_transform in threat_query.iter() {
             let distance_2 = (duck_transform.translation.xy() - threat_transform.translation.xy())
                 .length_squared();
-            if distance_2 < AVOID_THREATS_RADIUS_2
-                && (closest_threat.is_none() || distance_2 < closest_threat.unwrap().1)
+            if distance_2 < AVOID_THREATS_RADIUS_2 * threat_awareness * threat_awareness
+                && closest_threat
+                    .is_none_or(|(_, closest_distance_2)| distance_2 < closest_distance_2)
             {
                 closest_threat = Some((threat_transform.translation.xy(), distance_2));
             }
         }
 
-        if closest_threat.is_some() {
-            let direction =
-                (closest_threat.unwrap().0 - duck_transform.translation.xy()).normalize();
-            duck_velocity.0 -= direction * AVOID_THREATS_FACTOR * time.delta_seconds();
+        if let Some((threat_position, _)) = closest_threat {
+            let direction = (threat_position - duck_transform.translation.xy()).normalize();
+            duck_velocity.0 -= direction
+                * boid_tuning.avoid_threats_factor
+                * threat_awareness
+                * time.delta_seconds();
         }
     }
 }
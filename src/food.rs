@@ -1,25 +1,242 @@
-use crate::spawning;
+use crate::difficulty::CurrentDifficulty;
+use crate::rng::SimulationRng;
 use crate::spawning::LoadedAssets;
+use crate::{inspector, spawning};
 use bevy::prelude::*;
+use bevy::utils::HashMap;
 use bevy::window::PrimaryWindow;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
-pub const FOOD_PLACEMENT_COOLDOWN: f32 = 2.0;
+#[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash, Debug, Default)]
+pub enum FoodKind {
+    #[default]
+    Bread,
+    Pretzel,
+    Croissant,
+    Cake,
+    Donut,
+}
+
+impl FoodKind {
+    // In the order of the number keys that select them
+    pub const ALL: [FoodKind; 5] = [
+        FoodKind::Bread,
+        FoodKind::Pretzel,
+        FoodKind::Croissant,
+        FoodKind::Cake,
+        FoodKind::Donut,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FoodKind::Bread => "Bread",
+            FoodKind::Pretzel => "Pretzel",
+            FoodKind::Croissant => "Croissant",
+            FoodKind::Cake => "Cake",
+            FoodKind::Donut => "Donut",
+        }
+    }
+
+    pub fn sprite_path(self) -> &'static str {
+        match self {
+            FoodKind::Bread => "foods/bread.png",
+            FoodKind::Pretzel => "foods/pretzel.png",
+            FoodKind::Croissant => "foods/croissant.png",
+            FoodKind::Cake => "foods/cake.png",
+            FoodKind::Donut => "foods/donut.png",
+        }
+    }
+
+    pub fn cooldown(self) -> f32 {
+        match self {
+            FoodKind::Bread => 2.0,
+            FoodKind::Pretzel => 4.0,
+            FoodKind::Croissant | FoodKind::Donut => 6.0,
+            FoodKind::Cake => 8.0,
+        }
+    }
+
+    // How much hunger eating it takes away
+    pub fn nutrition(self) -> f32 {
+        match self {
+            FoodKind::Bread => 0.5,
+            FoodKind::Pretzel => 1.0,
+            FoodKind::Croissant | FoodKind::Cake | FoodKind::Donut => 0.3,
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct FoodRotted {
+    pub position: Vec2,
+}
 
 #[derive(Component)]
-pub struct Food;
+pub struct Food {
+    pub kind: FoodKind,
+    // Seconds since it was placed, food goes stale, then rotten and then rots away
+    pub age: f32,
+}
 
+const FOOD_STALE_AGE: f32 = 20.0;
+const FOOD_ROTTEN_AGE: f32 = 35.0;
+const FOOD_ROTTED_AWAY_AGE: f32 = 45.0;
+const FOOD_FADE_DURATION: f32 = 3.0;
+const ROTTEN_FOOD_HARM: f32 = 0.25;
+
+const STALE_FOOD_COLOR: Color = Color::srgb(0.85, 0.8, 0.6);
+const ROTTEN_FOOD_COLOR: Color = Color::srgb(0.55, 0.65, 0.35);
+
+impl Food {
+    pub fn is_rotten(&self) -> bool {
+        self.age >= FOOD_ROTTEN_AGE
+    }
+
+    // Rotten food makes ducks hungrier instead
+    pub fn nutrition(&self) -> f32 {
+        if self.is_rotten() {
+            -ROTTEN_FOOD_HARM
+        } else {
+            self.kind.nutrition()
+        }
+    }
+
+    pub fn boost(&self) -> Option<FoodBoost> {
+        if self.is_rotten() {
+            None
+        } else {
+            FoodBoost::from_food(self.kind)
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        let color = if self.is_rotten() {
+            ROTTEN_FOOD_COLOR
+        } else if self.age >= FOOD_STALE_AGE {
+            STALE_FOOD_COLOR
+        } else {
+            Color::WHITE
+        };
+        let fade = (FOOD_ROTTED_AWAY_AGE - self.age) / FOOD_FADE_DURATION;
+        color.with_alpha(fade.clamp(0.0, 1.0))
+    }
+}
+
+// Temporary effect of the last special food a duck ate
+#[derive(Component, Clone, Copy)]
+pub struct FoodBoost {
+    pub kind: FoodKind,
+    pub remaining_time: f32,
+}
+
+const FOOD_BOOST_DURATION: f32 = 10.0;
+const GROWTH_BOOST_FACTOR: f32 = 2.0;
+const BREEDING_BOOST_FACTOR: f32 = 2.0;
+const SPEED_BOOST_FACTOR: f32 = 1.5;
+
+impl FoodBoost {
+    pub fn from_food(kind: FoodKind) -> Option<Self> {
+        match kind {
+            FoodKind::Croissant | FoodKind::Cake | FoodKind::Donut => Some(Self {
+                kind,
+                remaining_time: FOOD_BOOST_DURATION,
+            }),
+            FoodKind::Bread | FoodKind::Pretzel => None,
+        }
+    }
+
+    pub fn growth_factor(&self) -> f32 {
+        if self.kind == FoodKind::Croissant {
+            GROWTH_BOOST_FACTOR
+        } else {
+            1.0
+        }
+    }
+
+    pub fn breeding_factor(&self) -> f32 {
+        if self.kind == FoodKind::Cake {
+            BREEDING_BOOST_FACTOR
+        } else {
+            1.0
+        }
+    }
+
+    pub fn speed_factor(&self) -> f32 {
+        if self.kind == FoodKind::Donut {
+            SPEED_BOOST_FACTOR
+        } else {
+            1.0
+        }
+    }
+}
+
+// One cooldown per food kind, they all cool down at the same time
 #[derive(Resource)]
-pub struct FoodPlacementTimer(pub Timer);
+pub struct FoodPlacementTimer(pub HashMap<FoodKind, Timer>);
+
+impl Default for FoodPlacementTimer {
+    fn default() -> Self {
+        Self(
+            FoodKind::ALL
+                .iter()
+                .map(|kind| (*kind, Timer::from_seconds(kind.cooldown(), TimerMode::Once)))
+                .collect(),
+        )
+    }
+}
+
+impl FoodPlacementTimer {
+    pub fn is_ready(&self, kind: FoodKind) -> bool {
+        self.0.get(&kind).is_none_or(Timer::finished)
+    }
+
+    pub fn remaining_secs(&self, kind: FoodKind) -> f32 {
+        self.0.get(&kind).map_or(0.0, Timer::remaining_secs)
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct SelectedFoodKind(pub FoodKind);
+
+// Food placed by the player, spawned on the next fixed tick so placements can be recorded and replayed
+#[derive(Resource, Default)]
+pub struct FoodPlacementQueue(pub Vec<(Vec2, FoodKind)>);
+
+pub fn system_select_food_kind(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut selected_food_kind: ResMut<SelectedFoodKind>,
+) {
+    const KEYS: [KeyCode; 5] = [
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+    ];
+
+    for (key, kind) in KEYS.iter().zip(FoodKind::ALL) {
+        if keyboard_input.just_pressed(*key) {
+            selected_food_kind.0 = kind;
+        }
+    }
+}
 
 pub fn system_place_food_on_input(
     time: Res<Time>,
     window_query: Query<&Window, With<PrimaryWindow>>,
     camera_query: Query<(&Camera, &GlobalTransform)>,
     mouse_button_input: Res<ButtonInput<MouseButton>>,
-    mut commands: Commands,
-    loaded_assets: Res<LoadedAssets>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    selected_food_kind: Res<SelectedFoodKind>,
+    current_difficulty: Res<CurrentDifficulty>,
     mut food_placement_timer: ResMut<FoodPlacementTimer>,
+    mut food_placement_queue: ResMut<FoodPlacementQueue>,
 ) {
+    for timer in food_placement_timer.0.values_mut() {
+        timer.tick(time.delta());
+    }
+
     let window = if let Ok(window) = window_query.get_single() {
         window
     } else {
@@ -39,15 +256,24 @@ pub fn system_place_food_on_input(
             return;
         };
 
-    if !food_placement_timer.0.tick(time.delta()).finished() {
+    let kind = selected_food_kind.0;
+    if !food_placement_timer.is_ready(kind) {
         return;
     }
 
-    if !mouse_button_input.just_pressed(MouseButton::Left) {
+    // Shift + left click inspects a duck instead
+    if !mouse_button_input.just_pressed(MouseButton::Left)
+        || inspector::is_inspect_input(&mouse_button_input, &keyboard_input)
+    {
         return;
     }
 
-    food_placement_timer.0.reset();
+    if let Some(timer) = food_placement_timer.0.get_mut(&kind) {
+        timer.set_duration(Duration::from_secs_f32(
+            kind.cooldown() * current_difficulty.settings.food_cooldown_factor,
+        ));
+        timer.reset();
+    }
 
     let world_position = if let Some(world_position) =
         camera.viewport_to_world_2d(camera_transform, cursor_position)
@@ -57,5 +283,50 @@ pub fn system_place_food_on_input(
         return;
     };
 
-    spawning::spawn_food(world_position, &mut commands, &loaded_assets);
+    food_placement_queue.0.push((world_position, kind));
+}
+
+pub fn system_apply_food_placements(
+    mut commands: Commands,
+    loaded_assets: Res<LoadedAssets>,
+    mut food_placement_queue: ResMut<FoodPlacementQueue>,
+    mut rng: ResMut<SimulationRng>,
+) {
+    for (position, kind) in food_placement_queue.0.drain(..) {
+        spawning::spawn_food(position, kind, &mut commands, &loaded_assets, &mut rng);
+    }
+}
+
+pub fn system_spoil_food(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut query: Query<(Entity, &Transform, &mut Food, &mut Sprite)>,
+    mut food_rotted_events: EventWriter<FoodRotted>,
+) {
+    for (entity, transform, mut food, mut sprite) in query.iter_mut() {
+        food.age += time.delta_seconds();
+
+        if food.age >= FOOD_ROTTED_AWAY_AGE {
+            commands.entity(entity).despawn();
+            food_rotted_events.send(FoodRotted {
+                position: transform.translation.xy(),
+            });
+            continue;
+        }
+
+        sprite.color = food.color();
+    }
+}
+
+pub fn system_update_food_boosts(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut FoodBoost)>,
+) {
+    for (entity, mut food_boost) in query.iter_mut() {
+        food_boost.remaining_time -= time.delta_seconds();
+        if food_boost.remaining_time <= 0.0 {
+            commands.entity(entity).remove::<FoodBoost>();
+        }
+    }
 }
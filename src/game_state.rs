@@ -1,8 +1,10 @@
 use crate::player::PlayerStats;
-use crate::{life_cycles, player, spawning, ui};
+use crate::rng::SimulationRng;
+use crate::{camera, difficulty, director, food, life_cycles, lineage, player, scare, ui};
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
+#[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States, Serialize, Deserialize)]
 pub enum GameState {
     #[default]
     Paused,
@@ -15,14 +17,32 @@ pub enum GameState {
 #[derive(Component)]
 pub struct RemoveOnRestart;
 
+// Like `in_state(GameState::Running)`, but already false once the round is about to end,
+// so no extra fixed ticks run in the frame before the transition is applied
+pub fn is_simulation_running(
+    game_state: Res<State<GameState>>,
+    next_game_state: Res<NextState<GameState>>,
+) -> bool {
+    *game_state.get() == GameState::Running && matches!(*next_game_state, NextState::Unchanged)
+}
+
 pub fn system_restart_game(
     mut commands: Commands,
     query: Query<Entity, With<RemoveOnRestart>>,
     mut game_state: ResMut<NextState<GameState>>,
-    mut current_threats: ResMut<spawning::CurrentThreats>,
     mut player_stats: ResMut<player::PlayerStats>,
+    mut threat_director: ResMut<director::ThreatDirector>,
+    mut family_tree: ResMut<lineage::FamilyTree>,
+    mut scare_queue: ResMut<scare::ScareQueue>,
+    mut food_placement_queue: ResMut<food::FoodPlacementQueue>,
+    mut rng: ResMut<SimulationRng>,
 ) {
-    current_threats.0 = 0;
+    rng.reseed();
+    *threat_director = director::ThreatDirector::default();
+    *family_tree = lineage::FamilyTree::default();
+    // Scares and food from the last moments of the old round
+    scare_queue.0.clear();
+    food_placement_queue.0.clear();
     player_stats.score = 0;
     player_stats.ducks_born = 0;
     player_stats.is_simulating = false;
@@ -33,40 +53,30 @@ pub fn system_restart_game(
     }
 }
 
-pub fn system_reset_remaining_time(mut player_stats: ResMut<player::PlayerStats>) {
+pub fn system_reset_remaining_time(
+    mut player_stats: ResMut<player::PlayerStats>,
+    current_difficulty: Res<difficulty::CurrentDifficulty>,
+) {
     if player_stats.is_simulating {
         return;
     }
-    player_stats.remaining_time = 240.0;
+    player_stats.remaining_time = current_difficulty.settings.round_length;
 }
 
 pub fn system_update_remaining_time(
     time: Res<Time>,
     mut player_stats: ResMut<player::PlayerStats>,
     mut game_state: ResMut<NextState<GameState>>,
-    mut remaining_time_widget: Query<(&mut Text, &ui::GameStatusWidgets)>,
 ) {
-    if !player_stats.is_simulating {
-        player_stats.remaining_time -= time.delta_seconds();
-        player_stats.remaining_time = player_stats.remaining_time.max(0.0);
+    if player_stats.is_simulating {
+        return;
+    }
 
-        for (mut text, widget) in remaining_time_widget.iter_mut() {
-            if *widget != ui::GameStatusWidgets::RemainingTime {
-                continue;
-            }
-            text.sections[0].value = format!("Remaining Time: {:.0}", player_stats.remaining_time);
-        }
+    player_stats.remaining_time -= time.delta_seconds();
+    player_stats.remaining_time = player_stats.remaining_time.max(0.0);
 
-        if player_stats.remaining_time <= 0.0 {
-            game_state.set(GameState::TimeOver);
-        }
-    } else {
-        for (mut text, widget) in remaining_time_widget.iter_mut() {
-            if *widget != ui::GameStatusWidgets::RemainingTime {
-                continue;
-            }
-            text.sections[0].value = "Remaining Time: Endless".to_string();
-        }
+    if player_stats.remaining_time <= 0.0 {
+        game_state.set(GameState::TimeOver);
     }
 }
 
@@ -94,6 +104,11 @@ pub fn system_check_game_over_condition(
 
 pub fn system_update_game_status_ui(
     player_stats: Res<PlayerStats>,
+    selected_food_kind: Res<food::SelectedFoodKind>,
+    food_placement_timer: Res<food::FoodPlacementTimer>,
+    scare_timer: Res<scare::ScareTimer>,
+    threat_director: Res<director::ThreatDirector>,
+    camera_query: Query<&camera::CameraController>,
     mut game_status_widgets: Query<(&mut Text, &ui::GameStatusWidgets)>,
     ducks_query: Query<
         Entity,
@@ -106,13 +121,58 @@ pub fn system_update_game_status_ui(
 ) {
     for (mut text, widget) in game_status_widgets.iter_mut() {
         match *widget {
+            ui::GameStatusWidgets::RemainingTime => {
+                text.sections[0].value = if player_stats.is_simulating {
+                    "Remaining Time: Endless".to_string()
+                } else {
+                    format!("Remaining Time: {:.0}", player_stats.remaining_time)
+                };
+            }
             ui::GameStatusWidgets::DucklingsBorn => {
                 text.sections[0].value = format!("Ducks Born: {0}", player_stats.ducks_born);
             }
             ui::GameStatusWidgets::DucksAlive => {
                 text.sections[0].value = format!("Ducks Alive: {0}", ducks_query.iter().count());
             }
-            _ => {}
+            ui::GameStatusWidgets::SelectedFood => {
+                let kind = selected_food_kind.0;
+                text.sections[0].value = if food_placement_timer.is_ready(kind) {
+                    format!("Food [1-5]: {}", kind.name())
+                } else {
+                    format!(
+                        "Food [1-5]: {} ({:.0})",
+                        kind.name(),
+                        food_placement_timer.remaining_secs(kind).ceil()
+                    )
+                };
+            }
+            ui::GameStatusWidgets::FollowFlock => {
+                let follow_flock = camera_query
+                    .get_single()
+                    .is_ok_and(|camera_controller| camera_controller.follow_flock);
+                text.sections[0].value = if follow_flock {
+                    "Follow Flock [F]: On".to_string()
+                } else {
+                    "Follow Flock [F]: Off".to_string()
+                };
+            }
+            ui::GameStatusWidgets::ThreatWave => {
+                text.sections[0].value = if threat_director.is_wave_incoming() {
+                    "Threats incoming!".to_string()
+                } else {
+                    String::new()
+                };
+            }
+            ui::GameStatusWidgets::Scare => {
+                text.sections[0].value = if scare_timer.is_ready() {
+                    "Scare [Right Click / Space]: Ready".to_string()
+                } else {
+                    format!(
+                        "Scare [Right Click / Space]: {:.0}",
+                        scare_timer.0.remaining_secs().ceil()
+                    )
+                };
+            }
         }
     }
 }
@@ -0,0 +1,104 @@
+use crate::rng::SimulationRng;
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+// Heritable traits of a duck, passed on to its eggs by `breeding::system_breeding`.
+// Traits are factors on the normal behaviour, 1.0 being an average duck.
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct Genome {
+    // Top speed, fast ducks also get hungry quicker
+    pub speed: f32,
+    // Sprite size, big ducks get hungry slower
+    pub size: f32,
+    // Multiplies the sprite colour
+    pub tint: (f32, f32, f32),
+    // How quickly a couple breeds
+    pub fertility: f32,
+    // How far away threats are noticed and how hard they are avoided
+    pub threat_awareness: f32,
+}
+
+impl Default for Genome {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            size: 1.0,
+            tint: (1.0, 1.0, 1.0),
+            fertility: 1.0,
+            threat_awareness: 1.0,
+        }
+    }
+}
+
+const TRAIT_MIN: f32 = 0.5;
+const TRAIT_MAX: f32 = 1.5;
+// Tints only darken, so a tinted sprite never goes past its full colour
+const TINT_MIN: f32 = 0.4;
+const TINT_MAX: f32 = 1.0;
+// How much the ducks a round starts with differ from an average duck
+const FOUNDER_VARIATION: f32 = 0.1;
+const MUTATION_CHANCE: f32 = 0.2;
+const MUTATION_STRENGTH: f32 = 0.15;
+
+impl Genome {
+    // For the ducks a round starts with
+    pub fn random(rng: &mut SimulationRng) -> Self {
+        let mut vary = |min: f32, max: f32| {
+            (1.0 + (rng.gen::<f32>() - 0.5) * 2.0 * FOUNDER_VARIATION).clamp(min, max)
+        };
+        Self {
+            speed: vary(TRAIT_MIN, TRAIT_MAX),
+            size: vary(TRAIT_MIN, TRAIT_MAX),
+            tint: (
+                vary(TINT_MIN, TINT_MAX),
+                vary(TINT_MIN, TINT_MAX),
+                vary(TINT_MIN, TINT_MAX),
+            ),
+            fertility: vary(TRAIT_MIN, TRAIT_MAX),
+            threat_awareness: vary(TRAIT_MIN, TRAIT_MAX),
+        }
+    }
+
+    // Every trait lands somewhere between the parents' and sometimes mutates
+    pub fn inherit(parent1: &Genome, parent2: &Genome, rng: &mut SimulationRng) -> Self {
+        let mut mix = |trait1: f32, trait2: f32, min: f32, max: f32| {
+            let mut value = trait1 + (trait2 - trait1) * rng.gen::<f32>();
+            if rng.gen::<f32>() < MUTATION_CHANCE {
+                value += (rng.gen::<f32>() - 0.5) * 2.0 * MUTATION_STRENGTH;
+            }
+            value.clamp(min, max)
+        };
+        Self {
+            speed: mix(parent1.speed, parent2.speed, TRAIT_MIN, TRAIT_MAX),
+            size: mix(parent1.size, parent2.size, TRAIT_MIN, TRAIT_MAX),
+            tint: (
+                mix(parent1.tint.0, parent2.tint.0, TINT_MIN, TINT_MAX),
+                mix(parent1.tint.1, parent2.tint.1, TINT_MIN, TINT_MAX),
+                mix(parent1.tint.2, parent2.tint.2, TINT_MIN, TINT_MAX),
+            ),
+            fertility: mix(parent1.fertility, parent2.fertility, TRAIT_MIN, TRAIT_MAX),
+            threat_awareness: mix(
+                parent1.threat_awareness,
+                parent2.threat_awareness,
+                TRAIT_MIN,
+                TRAIT_MAX,
+            ),
+        }
+    }
+
+    pub fn tinted(&self, color: Color) -> Color {
+        let color = color.to_srgba();
+        Color::srgba(
+            color.red * self.tint.0,
+            color.green * self.tint.1,
+            color.blue * self.tint.2,
+            color.alpha,
+        )
+    }
+
+    pub fn hunger_factor(&self) -> f32 {
+        self.speed / self.size
+    }
+}
@@ -0,0 +1,61 @@
+use crate::genetics::Genome;
+use bevy::prelude::*;
+
+#[derive(Event)]
+pub struct DuckStarved {
+    pub position: Vec2,
+}
+
+// 0.0 is fed, a duck starves when it reaches 1.0. Eggs don't get hungry.
+#[derive(Component, Default)]
+pub struct Hunger(pub f32);
+
+impl Hunger {
+    pub fn is_fed(&self) -> bool {
+        self.0 < HUNGRY_THRESHOLD
+    }
+
+    // Negative nutrition, like from rotten food, makes the duck hungrier
+    pub fn eat(&mut self, nutrition: f32) {
+        self.0 = (self.0 - nutrition).clamp(0.0, 1.0);
+    }
+
+    // How fast the duck grows into its next life stage
+    pub fn growth_factor(&self) -> f32 {
+        if self.is_fed() {
+            1.0
+        } else {
+            HUNGRY_GROWTH_FACTOR
+        }
+    }
+}
+
+const HUNGER_PER_SECOND: f32 = 1.0 / 90.0;
+const HUNGRY_THRESHOLD: f32 = 0.5;
+const HUNGRY_GROWTH_FACTOR: f32 = 0.5;
+
+pub fn system_increase_hunger(time: Res<Time>, mut query: Query<(&mut Hunger, Option<&Genome>)>) {
+    for (mut hunger, genome) in query.iter_mut() {
+        let hunger_per_second = HUNGER_PER_SECOND * genome.map_or(1.0, Genome::hunger_factor);
+        hunger.0 = (hunger.0 + hunger_per_second * time.delta_seconds()).min(1.0);
+    }
+}
+
+pub fn system_starvation(
+    mut commands: Commands,
+    query: Query<(Entity, &Transform, &Hunger)>,
+    mut duck_starved_events: EventWriter<DuckStarved>,
+) {
+    for (entity, transform, hunger) in query.iter() {
+        if hunger.0 < 1.0 {
+            continue;
+        }
+
+        if let Some(mut entity_cmd) = commands.get_entity(entity) {
+            entity_cmd.despawn();
+            duck_starved_events.send(DuckStarved {
+                position: transform.translation.xy(),
+            });
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use crate::breeding::{BreedingProgress, Relationship, Sambo, BREEDING_DURATION};
+use crate::difficulty::CurrentDifficulty;
+use crate::food::FoodBoost;
+use crate::hunger::Hunger;
+use crate::life_cycles::{self, Adult, Duckling, Egg, Elder, Juvenile, LifeCycleTime, LifeStage};
+use crate::lineage::{Ancestry, FamilyTree};
+use crate::movement::Velocity;
+use crate::ui::DuckInspectorText;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+// The duck shown in the inspector panel, picked with shift + left click
+#[derive(Resource, Default)]
+pub struct SelectedDuck(pub Option<Entity>);
+
+// A plain left click always places food, ducks are often clicked to feed the flock
+pub fn is_inspect_input(
+    mouse_button_input: &ButtonInput<MouseButton>,
+    keyboard_input: &ButtonInput<KeyCode>,
+) -> bool {
+    mouse_button_input.just_pressed(MouseButton::Left)
+        && keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight])
+}
+
+// Finds the duck under the cursor
+#[derive(SystemParam)]
+pub struct DuckPicker<'w, 's> {
+    window_query: Query<'w, 's, &'static Window, With<PrimaryWindow>>,
+    camera_query: Query<'w, 's, (&'static Camera, &'static GlobalTransform)>,
+    texture_atlas_layouts: Res<'w, Assets<TextureAtlasLayout>>,
+    images: Res<'w, Assets<Image>>,
+    duck_query: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Transform,
+            &'static Handle<Image>,
+            Option<&'static TextureAtlas>,
+        ),
+        Or<(With<Egg>, With<Duckling>, With<Juvenile>, With<Adult>)>,
+    >,
+}
+
+impl DuckPicker<'_, '_> {
+    // The topmost duck whose sprite is under the cursor
+    pub fn duck_under_cursor(&self) -> Option<Entity> {
+        let window = self.window_query.get_single().ok()?;
+        let cursor_position = window.cursor_position()?;
+        let (camera, camera_transform) = self.camera_query.get_single().ok()?;
+        let world_position = camera.viewport_to_world_2d(camera_transform, cursor_position)?;
+
+        let mut topmost_duck: Option<(Entity, f32)> = None;
+        for (entity, transform, image, texture_atlas) in self.duck_query.iter() {
+            // Eggs aren't animated, they use the whole image
+            let atlas_rect = texture_atlas.and_then(|texture_atlas| {
+                self.texture_atlas_layouts
+                    .get(&texture_atlas.layout)
+                    .and_then(|layout| layout.textures.get(texture_atlas.index))
+            });
+            let sprite_size = if let Some(rect) = atlas_rect {
+                rect.size().as_vec2() * transform.scale.xy().abs()
+            } else if let Some(image) = self.images.get(image) {
+                image.size().as_vec2() * transform.scale.xy().abs()
+            } else {
+                continue;
+            };
+
+            let offset = (world_position - transform.translation.xy()).abs();
+            if offset.x > sprite_size.x * 0.5 || offset.y > sprite_size.y * 0.5 {
+                continue;
+            }
+            if topmost_duck.is_none_or(|(_, topmost_z)| transform.translation.z > topmost_z) {
+                topmost_duck = Some((entity, transform.translation.z));
+            }
+        }
+
+        topmost_duck.map(|(entity, _)| entity)
+    }
+}
+
+// Clicking next to every duck deselects
+pub fn system_select_duck_on_input(
+    mouse_button_input: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    duck_picker: DuckPicker,
+    mut selected_duck: ResMut<SelectedDuck>,
+) {
+    if !is_inspect_input(&mouse_button_input, &keyboard_input) {
+        return;
+    }
+
+    selected_duck.0 = duck_picker.duck_under_cursor();
+}
+
+pub fn system_update_inspector_ui(
+    mut selected_duck: ResMut<SelectedDuck>,
+    current_difficulty: Res<CurrentDifficulty>,
+    family_tree: Res<FamilyTree>,
+    duck_query: Query<(
+        Entity,
+        &LifeCycleTime,
+        &Ancestry,
+        Option<&Velocity>,
+        Option<&Sambo>,
+        Option<&Hunger>,
+        Option<&FoodBoost>,
+        Has<Duckling>,
+        Has<Juvenile>,
+        Has<Adult>,
+        Has<Elder>,
+    )>,
+    relationship_query: Query<(&Relationship, &BreedingProgress)>,
+    ancestry_query: Query<&Ancestry>,
+    mut inspector_text_query: Query<&mut Text, With<DuckInspectorText>>,
+) {
+    let mut text = if let Ok(text) = inspector_text_query.get_single_mut() {
+        text
+    } else {
+        return;
+    };
+
+    // The selected duck might have been eaten, starved or died of old age since
+    let (
+        entity,
+        life_cycle_time,
+        ancestry,
+        velocity,
+        sambo,
+        hunger,
+        food_boost,
+        is_duckling,
+        is_juvenile,
+        is_adult,
+        is_elder,
+    ) = if let Some(duck) = selected_duck
+        .0
+        .and_then(|entity| duck_query.get(entity).ok())
+    {
+        duck
+    } else {
+        selected_duck.0 = None;
+        text.sections[0].value = "Shift + Click a duck to inspect it".to_string();
+        return;
+    };
+
+    let stage = if is_elder {
+        LifeStage::Elder
+    } else if is_adult {
+        LifeStage::Adult
+    } else if is_juvenile {
+        LifeStage::Juvenile
+    } else if is_duckling {
+        LifeStage::Duckling
+    } else {
+        LifeStage::Egg
+    };
+    let next_stage = if is_elder {
+        "Dies Of Old Age In"
+    } else {
+        "Next Stage In"
+    };
+    let time_factor = current_difficulty.settings.time_factor;
+    // At the rate `life_cycles::system_decrease_lifecycle_time` counts down right now
+    let growth_factor = life_cycles::growth_factor(hunger, food_boost, is_elder);
+    let velocity = velocity.map_or(Vec2::ZERO, |velocity| velocity.0);
+
+    let mut lines = vec![
+        format!(
+            "Duck #{} (Generation {})",
+            ancestry.id.0, ancestry.generation
+        ),
+        format!("Stage: {}", stage.name()),
+        format!(
+            "{next_stage}: {:.0}s",
+            (life_cycle_time.0 * time_factor / growth_factor)
+                .max(0.0)
+                .ceil()
+        ),
+        format!("Velocity: {:.0}, {:.0}", velocity.x, velocity.y),
+    ];
+
+    let relationship =
+        sambo.and_then(|sambo| relationship_query.get(sambo.relationship_entity).ok());
+    if let Some((relationship, breeding_progress)) = relationship {
+        let partner = if relationship.partner1 == entity {
+            relationship.partner2
+        } else {
+            relationship.partner1
+        };
+        lines.push(match ancestry_query.get(partner) {
+            Ok(partner_ancestry) => format!("Partner: Duck #{}", partner_ancestry.id.0),
+            Err(_) => "Partner: Unknown".to_string(),
+        });
+        lines.push(format!(
+            "Breeding: {:.0}%",
+            (breeding_progress.0 / (BREEDING_DURATION * time_factor) * 100.0).min(100.0)
+        ));
+    } else if is_adult {
+        lines.push("Partner: None".to_string());
+    }
+
+    lines.push(format!("Children: {}", family_tree.offspring(ancestry.id)));
+    text.sections[0].value = lines.join("\n");
+}
@@ -1,23 +1,282 @@
+use crate::difficulty::{CurrentDifficulty, Difficulty};
 use crate::{player, ui};
 use bevy::prelude::*;
-use bevy_jornet::{Leaderboard, Score};
+use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 
-pub fn system_setup_leaderboard(mut leaderboard: ResMut<Leaderboard>) {
-    // `None` will create a new user with a random name
-    leaderboard.create_player(None);
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Score {
+    pub player: String,
+    pub score: f32,
+    // Scores from before there were difficulties count as normal
+    #[serde(default)]
+    pub difficulty: Difficulty,
+}
+
+// Where scores are sent to and read from. The `Leaderboard` resource is the online Jornet
+// leaderboard with the `jornet` feature, and a `LocalLeaderboard` kept on disk otherwise.
+pub trait LeaderboardBackend: Resource {
+    // Shown in the leaderboard title, e.g. "Global"
+    const SCOPE: &'static str;
+
+    fn setup(&mut self);
+    fn player_name(&self) -> Option<String>;
+    fn submit_score(&mut self, score: f32, difficulty: Difficulty);
+    // Only the scores reached on `difficulty`
+    fn scores(&self, difficulty: Difficulty) -> Vec<Score>;
+}
+
+#[cfg(feature = "jornet")]
+pub type Leaderboard = bevy_jornet::Leaderboard;
+#[cfg(not(feature = "jornet"))]
+pub type Leaderboard = LocalLeaderboard;
+
+pub struct LeaderboardPlugin;
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        #[cfg(feature = "jornet")]
+        app.add_plugins(jornet_plugin(Difficulty::default()))
+            .insert_resource(JornetLeaderboards::new())
+            .add_systems(
+                Update,
+                system_select_jornet_leaderboard.before(system_display_leaderboard),
+            );
+        #[cfg(not(feature = "jornet"))]
+        app.insert_resource(LocalLeaderboard::load());
+
+        app.insert_resource(ProcessedLeaderboard::default())
+            .add_systems(Startup, system_setup_leaderboard)
+            .add_systems(Update, system_display_leaderboard);
+    }
+}
+
+// Every difficulty has a Jornet leaderboard of its own. Normal uses `JORNET_LEADERBOARD_ID` and
+// `JORNET_LEADERBOARD_KEY`, the others add their name, e.g. `JORNET_LEADERBOARD_ID_EASY`.
+#[cfg(feature = "jornet")]
+fn jornet_plugin(difficulty: Difficulty) -> bevy_jornet::JornetPlugin {
+    let (id, key) = match difficulty {
+        Difficulty::Easy => (
+            env!("JORNET_LEADERBOARD_ID_EASY"),
+            env!("JORNET_LEADERBOARD_KEY_EASY"),
+        ),
+        Difficulty::Normal => (
+            env!("JORNET_LEADERBOARD_ID"),
+            env!("JORNET_LEADERBOARD_KEY"),
+        ),
+        Difficulty::Hard => (
+            env!("JORNET_LEADERBOARD_ID_HARD"),
+            env!("JORNET_LEADERBOARD_KEY_HARD"),
+        ),
+        Difficulty::Custom => (
+            env!("JORNET_LEADERBOARD_ID_CUSTOM"),
+            env!("JORNET_LEADERBOARD_KEY_CUSTOM"),
+        ),
+    };
+    bevy_jornet::JornetPlugin::with_leaderboard(id, key)
+}
+
+// The boards of the difficulties that aren't selected. The `Leaderboard` resource holds the
+// selected one, the Jornet systems only look at that.
+#[cfg(feature = "jornet")]
+#[derive(Resource)]
+pub struct JornetLeaderboards {
+    selected: Difficulty,
+    others: bevy::utils::HashMap<Difficulty, bevy_jornet::Leaderboard>,
+}
+
+#[cfg(feature = "jornet")]
+impl JornetLeaderboards {
+    fn new() -> Self {
+        // Only `JornetPlugin` can make a `Leaderboard`. Its systems are only needed once, so the
+        // other boards are made in an app that is thrown away right after.
+        let mut boards_app = App::empty();
+        let others = Difficulty::ALL
+            .into_iter()
+            .filter(|difficulty| *difficulty != Difficulty::default())
+            .map(|difficulty| {
+                jornet_plugin(difficulty).build(&mut boards_app);
+                let leaderboard = boards_app
+                    .world_mut()
+                    .remove_resource::<bevy_jornet::Leaderboard>()
+                    .expect("JornetPlugin inserts the Leaderboard");
+                (difficulty, leaderboard)
+            })
+            .collect();
+        Self {
+            selected: Difficulty::default(),
+            others,
+        }
+    }
+
+    // Swaps the board of `difficulty` into `leaderboard`, the player goes along
+    fn select(&mut self, difficulty: Difficulty, leaderboard: &mut bevy_jornet::Leaderboard) {
+        let mut next = if let Some(next) = self.others.remove(&difficulty) {
+            next
+        } else {
+            return;
+        };
+        if let Some(player) = leaderboard.get_player() {
+            next.as_player(player.clone());
+        }
+        next.refresh_leaderboard();
+        let previous = std::mem::replace(leaderboard, next);
+        self.others.insert(self.selected, previous);
+        self.selected = difficulty;
+    }
+}
+
+// Waits for the player, the one being created would be lost with its board otherwise
+#[cfg(feature = "jornet")]
+pub fn system_select_jornet_leaderboard(
+    current_difficulty: Res<CurrentDifficulty>,
+    mut leaderboard: ResMut<Leaderboard>,
+    mut jornet_leaderboards: ResMut<JornetLeaderboards>,
+) {
+    if current_difficulty.difficulty == jornet_leaderboards.selected
+        || leaderboard.get_player().is_none()
+    {
+        return;
+    }
+    jornet_leaderboards.select(current_difficulty.difficulty, &mut leaderboard);
+}
+
+#[cfg(feature = "jornet")]
+impl LeaderboardBackend for bevy_jornet::Leaderboard {
+    const SCOPE: &'static str = "Global";
+
+    fn setup(&mut self) {
+        // `None` will create a new user with a random name
+        self.create_player(None);
+
+        self.refresh_leaderboard();
+    }
+
+    fn player_name(&self) -> Option<String> {
+        self.get_player().map(|player| player.name.clone())
+    }
+
+    fn submit_score(&mut self, score: f32, _difficulty: Difficulty) {
+        self.send_score(score);
+    }
 
-    leaderboard.refresh_leaderboard();
+    fn scores(&self, difficulty: Difficulty) -> Vec<Score> {
+        self.get_leaderboard()
+            .into_iter()
+            .map(|score| Score {
+                player: score.player,
+                score: score.score,
+                difficulty,
+            })
+            .collect()
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+const LOCAL_LEADERBOARD_PATH: &str = "leaderboard.ron";
+
+// Scores of everyone who played on this machine, saved to `LOCAL_LEADERBOARD_PATH`.
+// On the web it only lasts for the session.
+#[derive(Resource, Serialize, Deserialize, Default)]
+pub struct LocalLeaderboard {
+    player: Option<String>,
+    scores: Vec<Score>,
+}
+
+impl LocalLeaderboard {
+    pub fn load() -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Ok(contents) = std::fs::read_to_string(LOCAL_LEADERBOARD_PATH) {
+            match ron::de::from_str(&contents) {
+                Ok(local_leaderboard) => return local_leaderboard,
+                Err(error) => warn!("Could not parse {LOCAL_LEADERBOARD_PATH}: {error}"),
+            }
+        }
+        Self::default()
+    }
+
+    fn save(&self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let result = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+                .map_err(|error| error.to_string())
+                .and_then(|contents| {
+                    std::fs::write(LOCAL_LEADERBOARD_PATH, contents)
+                        .map_err(|error| error.to_string())
+                });
+            if let Err(error) = result {
+                warn!("Could not save {LOCAL_LEADERBOARD_PATH}: {error}");
+            }
+        }
+    }
+}
+
+impl LeaderboardBackend for LocalLeaderboard {
+    const SCOPE: &'static str = "Local";
+
+    fn setup(&mut self) {
+        if self.player.is_some() {
+            return;
+        }
+        let player_name = std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or_else(|_| "Player".to_string());
+        self.player = Some(player_name);
+        self.save();
+    }
+
+    fn player_name(&self) -> Option<String> {
+        self.player.clone()
+    }
+
+    fn submit_score(&mut self, score: f32, difficulty: Difficulty) {
+        let player = if let Some(player) = self.player.clone() {
+            player
+        } else {
+            return;
+        };
+        self.scores.push(Score {
+            player,
+            score,
+            difficulty,
+        });
+        self.save();
+    }
+
+    // Scores of all difficulties are kept together and filtered when read
+    fn scores(&self, difficulty: Difficulty) -> Vec<Score> {
+        self.scores
+            .iter()
+            .filter(|score| score.difficulty == difficulty)
+            .cloned()
+            .collect()
+    }
+}
+
+pub fn system_setup_leaderboard(mut leaderboard: ResMut<Leaderboard>) {
+    leaderboard.setup();
 }
 
 #[allow(dead_code)]
-pub fn system_add_test_score(leaderboard: ResMut<Leaderboard>) {
-    if leaderboard.get_player().is_none() {
+pub fn system_add_test_score(mut leaderboard: ResMut<Leaderboard>) {
+    if leaderboard.player_name().is_none() {
         return;
     }
 
     let random_score = rand::random::<f32>() * 100.0;
-    leaderboard.send_score(random_score);
+    leaderboard.submit_score(random_score, Difficulty::Normal);
+}
+
+// Names the backend and the difficulty the scores are from
+#[derive(Component)]
+pub struct LeaderboardTitle;
+
+pub fn leaderboard_title(difficulty: Difficulty) -> String {
+    format!(
+        "Leaderboard  ({}, {})",
+        Leaderboard::SCOPE,
+        difficulty.name()
+    )
 }
 
 #[derive(Component)]
@@ -39,9 +298,11 @@ pub fn system_display_leaderboard(
     root_ui: Query<(Entity, &LeaderboardMarker)>,
     mut processed_leaderboard: ResMut<ProcessedLeaderboard>,
     player_stats: Res<player::PlayerStats>,
+    current_difficulty: Res<CurrentDifficulty>,
+    mut title_query: Query<&mut Text, With<LeaderboardTitle>>,
 ) {
-    let player = if let Some(player) = leaderboard.get_player() {
-        player
+    let player_name = if let Some(player_name) = leaderboard.player_name() {
+        player_name
     } else {
         return;
     };
@@ -50,9 +311,16 @@ pub fn system_display_leaderboard(
     processed_leaderboard.last_player_score = player_stats.score;
     let player_score: f32 = player_stats.score as f32;
 
-    let leaderboard_changed = leaderboard.is_changed();
+    let difficulty = current_difficulty.difficulty;
+    let leaderboard_changed = leaderboard.is_changed() || current_difficulty.is_changed();
+    if leaderboard_changed {
+        for mut text in title_query.iter_mut() {
+            text.sections[0].value = leaderboard_title(difficulty);
+        }
+    }
+
     if leaderboard_changed {
-        processed_leaderboard.scores = leaderboard.get_leaderboard();
+        processed_leaderboard.scores = leaderboard.scores(difficulty);
         processed_leaderboard
             .scores
             .sort_unstable_by(|s1, s2| s2.score.partial_cmp(&s1.score).unwrap_or(Ordering::Equal));
@@ -84,7 +352,7 @@ pub fn system_display_leaderboard(
                     root_entity,
                     marker,
                     &processed_leaderboard.scores,
-                    &player.name,
+                    &player_name,
                     player_score,
                 );
             } else {
@@ -96,7 +364,7 @@ pub fn system_display_leaderboard(
                     root_entity,
                     marker,
                     &processed_leaderboard.scores,
-                    &player.name,
+                    &player_name,
                     player_score,
                 );
 
@@ -119,7 +387,7 @@ pub fn system_display_leaderboard(
                     root_entity,
                     marker,
                     &processed_leaderboard.scores,
-                    &player.name,
+                    &player_name,
                     player_score,
                 );
             }
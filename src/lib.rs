@@ -0,0 +1,37 @@
+// Bevy code commonly triggers these lints and they may be important signals
+// about code quality. They are sometimes hard to avoid though, and the CI
+// workflow treats them as errors, so this allows them throughout the project.
+// Feel free to delete this line.
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+
+pub mod audio;
+pub mod boid;
+pub mod breeding;
+pub mod camera;
+pub mod cursor;
+pub mod difficulty;
+pub mod director;
+pub mod duck_boid;
+pub mod food;
+pub mod game_state;
+pub mod genetics;
+pub mod hunger;
+pub mod inspector;
+pub mod leaderboard;
+pub mod life_cycles;
+pub mod lineage;
+pub mod movement;
+pub mod player;
+pub mod replay;
+pub mod rng;
+pub mod save_game;
+pub mod scare;
+pub mod simulation;
+pub mod spatial_grid;
+pub mod spawning;
+pub mod sprite_animation;
+pub mod terrain;
+pub mod threat_boid;
+pub mod tuning;
+pub mod ui;
+pub mod vfx;
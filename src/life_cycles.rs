@@ -1,6 +1,14 @@
-use crate::boid::Boid;
-use crate::{audio, duck_boid, movement, player, spawning, sprite_animation, vfx};
+use crate::boid::{Boid, BoidProfile};
+use crate::difficulty::CurrentDifficulty;
+use crate::food::FoodBoost;
+use crate::genetics::Genome;
+use crate::hunger::Hunger;
+use crate::rng::SimulationRng;
+use crate::tuning::BoidTuning;
+use crate::{duck_boid, movement, player, spawning, sprite_animation};
 use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 #[derive(Component)]
 pub struct Egg;
@@ -10,126 +18,270 @@ pub struct Duckling;
 pub struct Juvenile;
 #[derive(Component)]
 pub struct Adult;
+// Old adults, they keep the `Adult` marker and still pair up, but breed slower until they die
+#[derive(Component)]
+pub struct Elder;
 
 #[derive(Component, Default)]
 pub struct LifeCycleTime(pub f32);
 
-pub const TIME_FACTOR: f32 = 1.0;
-pub const EGG_HATCH_TIME_MIN: f32 = 5.0 * TIME_FACTOR;
-pub const EGG_HATCH_TIME_MAX: f32 = 11.0 * TIME_FACTOR;
+#[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Debug)]
+pub enum LifeStage {
+    Egg,
+    Duckling,
+    Juvenile,
+    Adult,
+    Elder,
+}
+
+impl LifeStage {
+    pub fn name(self) -> &'static str {
+        match self {
+            LifeStage::Egg => "Egg",
+            LifeStage::Duckling => "Duckling",
+            LifeStage::Juvenile => "Juvenile",
+            LifeStage::Adult => "Adult",
+            LifeStage::Elder => "Elder",
+        }
+    }
+}
+
+// How a duck looks in each stage of its life
+pub struct StageSprite {
+    pub image: Handle<Image>,
+    pub atlas: Handle<TextureAtlasLayout>,
+    pub animation_indices: sprite_animation::AnimationIndices,
+    pub animation_timer: sprite_animation::AnimationTimer,
+    pub z_value: f32,
+    pub scale: f32,
+    pub color: Color,
+}
+
+pub fn stage_sprite(stage: LifeStage, loaded_assets: &spawning::LoadedAssets) -> StageSprite {
+    let (image, atlas, last_index, frame_duration, timer_mode, z_value, scale) = match stage {
+        LifeStage::Egg => (
+            loaded_assets.egg_sprite.clone(),
+            Handle::default(),
+            0,
+            0.25,
+            TimerMode::Once,
+            -1.0,
+            0.5,
+        ),
+        LifeStage::Duckling => (
+            loaded_assets.duckling_sprite.clone(),
+            loaded_assets.duckling_atlas.clone(),
+            3,
+            0.1,
+            TimerMode::Repeating,
+            0.0,
+            1.25,
+        ),
+        LifeStage::Juvenile => (
+            loaded_assets.juvenile_sprite.clone(),
+            loaded_assets.juvenile_atlas.clone(),
+            3,
+            0.15,
+            TimerMode::Repeating,
+            1.0,
+            1.5,
+        ),
+        LifeStage::Adult | LifeStage::Elder => (
+            loaded_assets.adult_sprite.clone(),
+            loaded_assets.adult_atlas.clone(),
+            3,
+            0.2,
+            TimerMode::Repeating,
+            2.0,
+            2.0,
+        ),
+    };
+
+    StageSprite {
+        image,
+        atlas,
+        animation_indices: sprite_animation::AnimationIndices {
+            first: 0,
+            last: last_index,
+            paused: false,
+        },
+        animation_timer: sprite_animation::AnimationTimer(Timer::from_seconds(
+            frame_duration,
+            timer_mode,
+        )),
+        z_value,
+        scale,
+        color: if stage == LifeStage::Elder {
+            ELDER_COLOR
+        } else {
+            Color::WHITE
+        },
+    }
+}
+
+#[derive(Event)]
+pub struct EggHatched {
+    pub position: Vec2,
+}
+
+#[derive(Event)]
+pub struct LifeCycleTransitioned {
+    pub position: Vec2,
+}
+
+#[derive(Event)]
+pub struct DuckDiedOfOldAge {
+    pub position: Vec2,
+}
+
+// In seconds on normal difficulty, `DifficultySettings::time_factor` scales them
+pub const EGG_HATCH_TIME_MIN: f32 = 5.0;
+pub const EGG_HATCH_TIME_MAX: f32 = 11.0;
+
+pub const DUCKLING_TO_JUVENILE_TIME_MIN: f32 = 10.0;
+pub const DUCKLING_TO_JUVENILE_TIME_MAX: f32 = 20.0;
+
+pub const JUVENILE_TO_ADULT_TIME_MIN: f32 = 8.0;
+pub const JUVENILE_TO_ADULT_TIME_MAX: f32 = 15.0;
 
-pub const DUCKLING_TO_JUVENILE_TIME_MIN: f32 = 10.0 * TIME_FACTOR;
-pub const DUCKLING_TO_JUVENILE_TIME_MAX: f32 = 20.0 * TIME_FACTOR;
+pub const ADULT_TO_ELDER_TIME_MIN: f32 = 60.0;
+pub const ADULT_TO_ELDER_TIME_MAX: f32 = 90.0;
 
-pub const JUVENILE_TO_ADULT_TIME_MIN: f32 = 8.0 * TIME_FACTOR;
-pub const JUVENILE_TO_ADULT_TIME_MAX: f32 = 15.0 * TIME_FACTOR;
+pub const ELDER_LIFESPAN_MIN: f32 = 20.0;
+pub const ELDER_LIFESPAN_MAX: f32 = 40.0;
 
-pub fn system_decrease_lifecycle_time(time: Res<Time>, mut query: Query<&mut LifeCycleTime>) {
-    for mut life_cycle_time in query.iter_mut() {
-        life_cycle_time.0 -= time.delta_seconds();
+const ELDER_COLOR: Color = Color::srgb(0.7, 0.7, 0.7);
+
+pub fn system_decrease_lifecycle_time(
+    time: Res<Time>,
+    current_difficulty: Res<CurrentDifficulty>,
+    mut query: Query<(
+        &mut LifeCycleTime,
+        Option<&Hunger>,
+        Option<&FoodBoost>,
+        Has<Elder>,
+    )>,
+) {
+    for (mut life_cycle_time, hunger, food_boost, is_elder) in query.iter_mut() {
+        life_cycle_time.0 -= time.delta_seconds() * growth_factor(hunger, food_boost, is_elder)
+            / current_difficulty.settings.time_factor;
+    }
+}
+
+// How fast the life cycle time of a duck runs out right now. Hunger slows down growing up, not
+// growing old.
+pub fn growth_factor(
+    hunger: Option<&Hunger>,
+    food_boost: Option<&FoodBoost>,
+    is_elder: bool,
+) -> f32 {
+    if is_elder {
+        1.0
+    } else {
+        hunger.map_or(1.0, Hunger::growth_factor) * food_boost.map_or(1.0, FoodBoost::growth_factor)
     }
 }
 
 fn transition_life_cycle(
     next_cycle_time_min: f32,
     next_cycle_time_max: f32,
-    z_value: f32,
-    scale: f32,
-    new_image: Handle<Image>,
-    new_texture_atlas: Handle<TextureAtlasLayout>,
-    new_animation_indices: sprite_animation::AnimationIndices,
-    new_animation_timer: sprite_animation::AnimationTimer,
+    new_stage_sprite: StageSprite,
+    sprite: &mut Sprite,
     entity_image: &mut Handle<Image>,
     texture_atlas: &mut TextureAtlas,
     animation_indices: &mut sprite_animation::AnimationIndices,
     animation_timer: &mut sprite_animation::AnimationTimer,
     life_cycle_time: &mut LifeCycleTime,
     transform: &mut Transform,
+    genome: &Genome,
+    rng: &mut SimulationRng,
 ) -> bool {
     if life_cycle_time.0 > 0.0 {
         return false;
     }
 
-    *animation_indices = new_animation_indices;
-    *animation_timer = new_animation_timer;
-    *entity_image = new_image;
-    texture_atlas.layout = new_texture_atlas;
+    *animation_indices = new_stage_sprite.animation_indices;
+    *animation_timer = new_stage_sprite.animation_timer;
+    sprite.color = genome.tinted(new_stage_sprite.color);
+    *entity_image = new_stage_sprite.image;
+    texture_atlas.layout = new_stage_sprite.atlas;
     texture_atlas.index = animation_indices.first;
 
-    transform.translation.z = z_value;
-    transform.scale = Vec3::splat(scale);
+    transform.translation.z = new_stage_sprite.z_value;
+    transform.scale = Vec3::splat(new_stage_sprite.scale * genome.size);
     life_cycle_time.0 =
-        rand::random::<f32>() * (next_cycle_time_max - next_cycle_time_min) + next_cycle_time_min;
+        rng.gen::<f32>() * (next_cycle_time_max - next_cycle_time_min) + next_cycle_time_min;
     true
 }
 
 pub fn system_hatch_eggs(
     mut commands: Commands,
     loaded_assets: Res<spawning::LoadedAssets>,
+    boid_tuning: Res<BoidTuning>,
+    mut rng: ResMut<SimulationRng>,
     mut player_stats: ResMut<player::PlayerStats>,
     mut query: Query<
         (
             Entity,
             &mut Transform,
             &mut LifeCycleTime,
+            &mut Sprite,
             &mut Handle<Image>,
             &mut TextureAtlas,
             &mut sprite_animation::AnimationIndices,
             &mut sprite_animation::AnimationTimer,
+            &Genome,
         ),
         With<Egg>,
     >,
-    active_audio_sources: Res<audio::ActiveAudioSources>,
+    mut egg_hatched_events: EventWriter<EggHatched>,
 ) {
     for (
         entity,
         mut transform,
         mut life_cycle_time,
+        mut sprite,
         mut entity_image,
         mut texture_atlas,
         mut animation_indices,
         mut animation_timer,
+        genome,
     ) in query.iter_mut()
     {
         let transitioned = transition_life_cycle(
             DUCKLING_TO_JUVENILE_TIME_MIN,
             DUCKLING_TO_JUVENILE_TIME_MAX,
-            0.0,
-            1.25,
-            loaded_assets.duckling_sprite.clone(),
-            loaded_assets.duckling_atlas.clone(),
-            sprite_animation::AnimationIndices {
-                first: 0,
-                last: 3,
-                paused: false,
-            },
-            sprite_animation::AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
+            stage_sprite(LifeStage::Duckling, &loaded_assets),
+            &mut sprite,
             &mut entity_image,
             &mut texture_atlas,
             &mut animation_indices,
             &mut animation_timer,
             &mut life_cycle_time,
             &mut transform,
+            genome,
+            &mut rng,
         );
 
         if transitioned {
             commands.entity(entity).remove::<Egg>();
             commands.entity(entity).try_insert(Duckling);
             commands.entity(entity).try_insert(Boid);
+            commands.entity(entity).try_insert(Hunger::default());
+            commands
+                .entity(entity)
+                .try_insert(boid_tuning.duckling_profile.clone());
 
-            vfx::spawn_egg_hatched_effect(
-                &mut commands,
-                &loaded_assets,
-                transform.translation.xy(),
-            );
-            audio::play_egg_pop(&loaded_assets, &mut commands, &active_audio_sources);
+            egg_hatched_events.send(EggHatched {
+                position: transform.translation.xy(),
+            });
             player_stats.ducks_born += 1;
             if !player_stats.is_simulating {
                 player_stats.score += 1;
             }
 
             let random_direction =
-                Vec2::new(rand::random::<f32>() - 0.5, rand::random::<f32>() - 0.5).normalize();
+                Vec2::new(rng.gen::<f32>() - 0.5, rng.gen::<f32>() - 0.5).normalize();
 
             let velocity_limits = movement::VelocityLimits::default();
             let velocity = random_direction * velocity_limits.min;
@@ -144,59 +296,61 @@ pub fn system_hatch_eggs(
 pub fn system_duckling_to_juvenile(
     mut commands: Commands,
     loaded_assets: Res<spawning::LoadedAssets>,
+    boid_tuning: Res<BoidTuning>,
+    mut rng: ResMut<SimulationRng>,
     mut query: Query<
         (
             Entity,
             &mut Transform,
             &mut LifeCycleTime,
+            &mut Sprite,
             &mut Handle<Image>,
             &mut TextureAtlas,
             &mut sprite_animation::AnimationIndices,
             &mut sprite_animation::AnimationTimer,
+            &Genome,
         ),
         With<Duckling>,
     >,
+    mut transition_events: EventWriter<LifeCycleTransitioned>,
 ) {
     for (
         entity,
         mut transform,
         mut life_cycle_time,
+        mut sprite,
         mut entity_image,
         mut texture_atlas,
         mut animation_indices,
         mut animation_timer,
+        genome,
     ) in query.iter_mut()
     {
         let transitioned = transition_life_cycle(
             JUVENILE_TO_ADULT_TIME_MIN,
             JUVENILE_TO_ADULT_TIME_MAX,
-            1.0,
-            1.5,
-            loaded_assets.juvenile_sprite.clone(),
-            loaded_assets.juvenile_atlas.clone(),
-            sprite_animation::AnimationIndices {
-                first: 0,
-                last: 3,
-                paused: false,
-            },
-            sprite_animation::AnimationTimer(Timer::from_seconds(0.15, TimerMode::Repeating)),
+            stage_sprite(LifeStage::Juvenile, &loaded_assets),
+            &mut sprite,
             &mut entity_image,
             &mut texture_atlas,
             &mut animation_indices,
             &mut animation_timer,
             &mut life_cycle_time,
             &mut transform,
+            genome,
+            &mut rng,
         );
 
         if transitioned {
             commands.entity(entity).remove::<Duckling>();
             commands.entity(entity).try_insert(Juvenile);
+            commands
+                .entity(entity)
+                .try_insert(boid_tuning.juvenile_profile.clone());
 
-            vfx::spawn_duck_cycle_transition_effect(
-                &mut commands,
-                &loaded_assets,
-                transform.translation.xy(),
-            );
+            transition_events.send(LifeCycleTransitioned {
+                position: transform.translation.xy(),
+            });
         }
     }
 }
@@ -204,62 +358,160 @@ pub fn system_duckling_to_juvenile(
 pub fn system_juvenile_to_adult(
     mut commands: Commands,
     loaded_assets: Res<spawning::LoadedAssets>,
+    boid_tuning: Res<BoidTuning>,
+    mut rng: ResMut<SimulationRng>,
     mut query: Query<
         (
             Entity,
             &mut Transform,
             &mut LifeCycleTime,
+            &mut Sprite,
             &mut Handle<Image>,
             &mut TextureAtlas,
             &mut sprite_animation::AnimationIndices,
             &mut sprite_animation::AnimationTimer,
+            &Genome,
         ),
         With<Juvenile>,
     >,
+    mut transition_events: EventWriter<LifeCycleTransitioned>,
 ) {
     for (
         entity,
         mut transform,
         mut life_cycle_time,
+        mut sprite,
         mut entity_image,
         mut texture_atlas,
         mut animation_indices,
         mut animation_timer,
+        genome,
     ) in query.iter_mut()
     {
         let transitioned = transition_life_cycle(
-            DUCKLING_TO_JUVENILE_TIME_MIN,
-            DUCKLING_TO_JUVENILE_TIME_MAX,
-            2.0,
-            2.0,
-            loaded_assets.adult_sprite.clone(),
-            loaded_assets.adult_atlas.clone(),
-            sprite_animation::AnimationIndices {
-                first: 0,
-                last: 3,
-                paused: false,
-            },
-            sprite_animation::AnimationTimer(Timer::from_seconds(0.2, TimerMode::Repeating)),
+            ADULT_TO_ELDER_TIME_MIN,
+            ADULT_TO_ELDER_TIME_MAX,
+            stage_sprite(LifeStage::Adult, &loaded_assets),
+            &mut sprite,
             &mut entity_image,
             &mut texture_atlas,
             &mut animation_indices,
             &mut animation_timer,
             &mut life_cycle_time,
             &mut transform,
+            genome,
+            &mut rng,
         );
 
         if transitioned {
             commands.entity(entity).remove::<Juvenile>();
             commands.entity(entity).try_insert(Adult);
+            commands
+                .entity(entity)
+                .try_insert(boid_tuning.adult_profile.clone());
             commands
                 .entity(entity)
                 .try_insert(duck_boid::CloseAdults::default());
 
-            vfx::spawn_duck_cycle_transition_effect(
-                &mut commands,
-                &loaded_assets,
-                transform.translation.xy(),
-            );
+            transition_events.send(LifeCycleTransitioned {
+                position: transform.translation.xy(),
+            });
         }
     }
 }
+
+pub fn system_adult_to_elder(
+    mut commands: Commands,
+    loaded_assets: Res<spawning::LoadedAssets>,
+    mut rng: ResMut<SimulationRng>,
+    mut query: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut LifeCycleTime,
+            &mut Sprite,
+            &mut Handle<Image>,
+            &mut TextureAtlas,
+            &mut sprite_animation::AnimationIndices,
+            &mut sprite_animation::AnimationTimer,
+            &Genome,
+        ),
+        (With<Adult>, Without<Elder>),
+    >,
+    mut transition_events: EventWriter<LifeCycleTransitioned>,
+) {
+    for (
+        entity,
+        mut transform,
+        mut life_cycle_time,
+        mut sprite,
+        mut entity_image,
+        mut texture_atlas,
+        mut animation_indices,
+        mut animation_timer,
+        genome,
+    ) in query.iter_mut()
+    {
+        let transitioned = transition_life_cycle(
+            ELDER_LIFESPAN_MIN,
+            ELDER_LIFESPAN_MAX,
+            stage_sprite(LifeStage::Elder, &loaded_assets),
+            &mut sprite,
+            &mut entity_image,
+            &mut texture_atlas,
+            &mut animation_indices,
+            &mut animation_timer,
+            &mut life_cycle_time,
+            &mut transform,
+            genome,
+            &mut rng,
+        );
+
+        if transitioned {
+            commands.entity(entity).try_insert(Elder);
+
+            transition_events.send(LifeCycleTransitioned {
+                position: transform.translation.xy(),
+            });
+        }
+    }
+}
+
+pub fn system_elder_death(
+    mut commands: Commands,
+    query: Query<(Entity, &Transform, &LifeCycleTime), With<Elder>>,
+    mut died_of_old_age_events: EventWriter<DuckDiedOfOldAge>,
+) {
+    for (entity, transform, life_cycle_time) in query.iter() {
+        if life_cycle_time.0 > 0.0 {
+            continue;
+        }
+
+        if let Some(mut entity_cmd) = commands.get_entity(entity) {
+            entity_cmd.despawn();
+            died_of_old_age_events.send(DuckDiedOfOldAge {
+                position: transform.translation.xy(),
+            });
+        }
+    }
+}
+
+// Re-applies the stage profiles after the tuning asset was (re)loaded
+pub fn system_refresh_boid_profiles(
+    boid_tuning: Res<BoidTuning>,
+    mut query: Query<(&mut BoidProfile, Has<Duckling>, Has<Juvenile>), With<Boid>>,
+) {
+    if !boid_tuning.is_changed() {
+        return;
+    }
+
+    for (mut boid_profile, is_duckling, is_juvenile) in query.iter_mut() {
+        *boid_profile = if is_duckling {
+            boid_tuning.duckling_profile.clone()
+        } else if is_juvenile {
+            boid_tuning.juvenile_profile.clone()
+        } else {
+            boid_tuning.adult_profile.clone()
+        };
+    }
+}
@@ -0,0 +1,163 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// Index into `FamilyTree::ducks`, stays valid after the duck is despawned
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct DuckId(pub u32);
+
+// Where a duck sits in the family tree. Ducks a round starts with are generation 1.
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct Ancestry {
+    pub id: DuckId,
+    pub parents: Option<(DuckId, DuckId)>,
+    pub generation: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct DuckRecord {
+    pub parents: Option<(DuckId, DuckId)>,
+    pub generation: u32,
+    // Eggs this duck laid together with any partner
+    pub offspring: u32,
+}
+
+// Every duck of the round, including the dead ones. Reset when the round restarts.
+#[derive(Resource, Serialize, Deserialize, Clone, Default)]
+pub struct FamilyTree {
+    ducks: Vec<DuckRecord>,
+}
+
+impl FamilyTree {
+    fn add(&mut self, parents: Option<(DuckId, DuckId)>, generation: u32) -> Ancestry {
+        let id = DuckId(self.ducks.len() as u32);
+        self.ducks.push(DuckRecord {
+            parents,
+            generation,
+            offspring: 0,
+        });
+        Ancestry {
+            id,
+            parents,
+            generation,
+        }
+    }
+
+    // For the ducks a round starts with, and saved ducks that have no ancestry yet
+    pub fn add_founder(&mut self) -> Ancestry {
+        self.add(None, 1)
+    }
+
+    pub fn add_offspring(&mut self, parent1: &Ancestry, parent2: &Ancestry) -> Ancestry {
+        for parent in [parent1.id, parent2.id] {
+            if let Some(record) = self.ducks.get_mut(parent.0 as usize) {
+                record.offspring += 1;
+            }
+        }
+        self.add(
+            Some((parent1.id, parent2.id)),
+            parent1.generation.max(parent2.generation) + 1,
+        )
+    }
+
+    pub fn offspring(&self, id: DuckId) -> u32 {
+        self.ducks
+            .get(id.0 as usize)
+            .map_or(0, |record| record.offspring)
+    }
+
+    pub fn generation_reached(&self) -> u32 {
+        self.ducks
+            .iter()
+            .map(|record| record.generation)
+            .max()
+            .unwrap_or(0)
+    }
+
+    // Founders that got at least one egg
+    pub fn dynasties(&self) -> usize {
+        self.ducks
+            .iter()
+            .filter(|record| record.parents.is_none() && record.offspring > 0)
+            .count()
+    }
+
+    // The earliest duck with the most eggs, if any duck laid one at all
+    pub fn most_prolific(&self) -> Option<(DuckId, &DuckRecord)> {
+        self.ducks
+            .iter()
+            .enumerate()
+            .filter(|(_, record)| record.offspring > 0)
+            .rev()
+            .max_by_key(|(_, record)| record.offspring)
+            .map(|(index, record)| (DuckId(index as u32), record))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn most_prolific_is_none_without_eggs() {
+        let mut family_tree = FamilyTree::default();
+        family_tree.add_founder();
+        family_tree.add_founder();
+        assert!(family_tree.most_prolific().is_none());
+    }
+
+    #[test]
+    fn most_prolific_picks_the_duck_with_the_most_eggs() {
+        let mut family_tree = FamilyTree::default();
+        let duck1 = family_tree.add_founder();
+        let duck2 = family_tree.add_founder();
+        let duck3 = family_tree.add_founder();
+        family_tree.add_offspring(&duck1, &duck2);
+        family_tree.add_offspring(&duck2, &duck3);
+
+        let (id, record) = family_tree.most_prolific().unwrap();
+        assert_eq!(id, duck2.id);
+        assert_eq!(record.offspring, 2);
+    }
+
+    #[test]
+    fn most_prolific_ties_go_to_the_earliest_duck() {
+        let mut family_tree = FamilyTree::default();
+        let duck1 = family_tree.add_founder();
+        let duck2 = family_tree.add_founder();
+        let duck3 = family_tree.add_founder();
+        let duck4 = family_tree.add_founder();
+        family_tree.add_offspring(&duck3, &duck4);
+        family_tree.add_offspring(&duck1, &duck2);
+
+        let (id, _) = family_tree.most_prolific().unwrap();
+        assert_eq!(id, duck1.id);
+    }
+
+    #[test]
+    fn offspring_are_a_generation_past_their_latest_parent() {
+        let mut family_tree = FamilyTree::default();
+        let duck1 = family_tree.add_founder();
+        let duck2 = family_tree.add_founder();
+        let child = family_tree.add_offspring(&duck1, &duck2);
+        let grandchild = family_tree.add_offspring(&child, &duck2);
+
+        assert_eq!(child.generation, 2);
+        assert_eq!(grandchild.generation, 3);
+        assert_eq!(grandchild.parents, Some((child.id, duck2.id)));
+        assert_eq!(family_tree.generation_reached(), 3);
+        assert_eq!(family_tree.offspring(duck2.id), 2);
+        assert_eq!(family_tree.offspring(grandchild.id), 0);
+    }
+
+    #[test]
+    fn dynasties_count_founders_with_eggs() {
+        let mut family_tree = FamilyTree::default();
+        let duck1 = family_tree.add_founder();
+        let duck2 = family_tree.add_founder();
+        family_tree.add_founder();
+        let child = family_tree.add_offspring(&duck1, &duck2);
+        family_tree.add_offspring(&child, &duck1);
+
+        assert_eq!(family_tree.dynasties(), 2);
+    }
+}
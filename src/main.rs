@@ -1,34 +1,18 @@
-// Bevy code commonly triggers these lints and they may be important signals
-// about code quality. They are sometimes hard to avoid though, and the CI
-// workflow treats them as errors, so this allows them throughout the project.
-// Feel free to delete this line.
-#![allow(clippy::too_many_arguments, clippy::type_complexity)]
-
-mod audio;
-mod boid;
-mod breeding;
-mod cursor;
-mod duck_boid;
-mod food;
-mod game_state;
-mod leaderboard;
-mod life_cycles;
-mod movement;
-mod player;
-mod spawning;
-mod sprite_animation;
-mod threat_boid;
-mod ui;
-mod vfx;
-
 use bevy::asset::AssetMetaCheck;
 use bevy::prelude::*;
 use bevy::winit::WinitWindows;
-use bevy_jornet::JornetPlugin;
+use ducky_boids::{
+    audio, camera, cursor, difficulty, director, food, game_state, inspector, leaderboard,
+    movement, replay, rng, save_game, scare, simulation, spawning, sprite_animation, terrain,
+    tuning, ui, vfx,
+};
 use winit::window::Icon;
 //use bevy::input::common_conditions::input_just_pressed;
 
 fn main() {
+    let replay_plugin = replay::ReplayPlugin::from_args();
+    let seed = replay_plugin.seed().unwrap_or_else(rng::startup_seed);
+
     App::new()
         .add_plugins(
             DefaultPlugins
@@ -49,49 +33,47 @@ fn main() {
                     ..Default::default()
                 }),
         )
-        .add_plugins(JornetPlugin::with_leaderboard(
-            env!("JORNET_LEADERBOARD_ID"),
-            env!("JORNET_LEADERBOARD_KEY"),
-            //&uuid::Uuid::NAMESPACE_X500.to_string(),
-            //&uuid::Uuid::NAMESPACE_X500.to_string(),
-        ))
+        .add_plugins(leaderboard::LeaderboardPlugin)
         .add_plugins(bevy_particle_systems::ParticleSystemPlugin)
-        .insert_resource(food::FoodPlacementTimer(Timer::from_seconds(
-            food::FOOD_PLACEMENT_COOLDOWN,
-            TimerMode::Once,
-        )))
-        .insert_resource(spawning::LoadedAssets::default())
-        .insert_resource(spawning::CurrentThreats::default())
+        .add_plugins(simulation::SimulationPlugin { seed })
+        .add_plugins(replay_plugin)
+        .add_plugins(save_game::SaveGamePlugin)
+        .init_asset::<tuning::BoidTuning>()
+        .init_asset_loader::<tuning::BoidTuningLoader>()
+        .init_asset::<director::ThreatDirectorConfig>()
+        .init_asset_loader::<director::ThreatDirectorConfigLoader>()
+        .init_asset::<difficulty::DifficultyPresets>()
+        .init_asset_loader::<difficulty::DifficultyPresetsLoader>()
+        .init_asset::<terrain::PondMap>()
+        .init_asset_loader::<terrain::PondMapLoader>()
+        .init_resource::<food::FoodPlacementTimer>()
+        .init_resource::<food::SelectedFoodKind>()
+        .init_resource::<scare::ScareTimer>()
+        .init_resource::<inspector::SelectedDuck>()
         .insert_resource(audio::ActiveAudioSources::default())
-        .insert_resource(leaderboard::ProcessedLeaderboard::default())
-        .insert_resource(player::PlayerStats::default())
-        .insert_state(game_state::GameState::Paused)
+        .insert_resource(tuning::BoidTuningHandle::default())
+        .insert_resource(director::ThreatDirectorConfigHandle::default())
+        .insert_resource(difficulty::DifficultyPresetsHandle::default())
+        .insert_resource(terrain::PondMapHandle::default())
+        .add_systems(PreStartup, spawning::load_assets)
         .add_systems(
             Startup,
             (
                 setup,
-                leaderboard::system_setup_leaderboard,
-                spawning::load_assets,
+                tuning::system_load_boid_tuning,
+                director::system_load_threat_director_config,
+                difficulty::system_load_difficulty_presets,
+                terrain::system_load_pond_map,
                 #[cfg(not(target_arch = "wasm32"))]
                 system_set_window_icon,
-                ui::system_create_main_menu.after(spawning::load_assets),
-                ui::system_spawn_leaderboard_ui.after(spawning::load_assets),
-                spawning::system_spawn_boids.after(spawning::load_assets),
-                spawning::system_spawn_threats.after(spawning::load_assets),
+                ui::system_create_main_menu,
+                ui::system_spawn_leaderboard_ui,
             ),
         )
         //.add_systems(
         //    OnEnter(game_state::GameState::Running),
         //    leaderboard::system_add_test_score,
         //)
-        .add_systems(
-            OnEnter(game_state::GameState::Restarting),
-            game_state::system_restart_game,
-        )
-        .add_systems(
-            OnExit(game_state::GameState::Restarting),
-            (spawning::system_spawn_boids, spawning::system_spawn_threats),
-        )
         .add_systems(
             OnEnter(game_state::GameState::TimeOver),
             (
@@ -108,68 +90,68 @@ fn main() {
             ),
         )
         .add_systems(
-            Update,
-            (
-                spawning::system_continuous_threat_spawning,
-                breeding::system_build_relationships,
-                breeding::system_update_relationships
-                    .after(breeding::system_build_relationships)
-                    .after(movement::system_movement),
-                breeding::system_breeding,
-                boid::system_boid_separation,
-                boid::system_boid_alignment_and_cohesion
-                    .after(boid::system_boid_separation)
-                    .before(movement::system_clamp_velocity),
-                duck_boid::system_boid_update_close_adults,
-                duck_boid::system_boids_food.before(movement::system_clamp_velocity),
-                duck_boid::system_boid_mating_attraction
-                    .after(duck_boid::system_boid_update_close_adults)
-                    .before(movement::system_clamp_velocity),
-                duck_boid::system_boids_ducklings_towards_adults
-                    .before(movement::system_clamp_velocity),
-                duck_boid::system_boids_avoid_threat.before(movement::system_clamp_velocity),
-                threat_boid::system_boid_towards_closest_duck
-                    .before(movement::system_clamp_velocity),
-                threat_boid::system_update_threat_animation.after(movement::system_clamp_velocity),
-                movement::system_clamp_velocity,
-                movement::system_flip_based_on_velocity,
-                movement::system_avoid_edges.after(movement::system_clamp_velocity),
-                movement::system_movement.after(movement::system_avoid_edges),
-                food::system_place_food_on_input,
-                sprite_animation::system_animate_sprites.after(movement::system_movement),
-            )
-                .run_if(in_state(game_state::GameState::Running)),
+            FixedFirst,
+            movement::system_restore_simulated_translation
+                .run_if(game_state::is_simulation_running),
+        )
+        .add_systems(
+            FixedPostUpdate,
+            movement::system_store_simulated_translation.run_if(game_state::is_simulation_running),
         )
         .add_systems(
             Update,
             (
-                life_cycles::system_decrease_lifecycle_time,
-                life_cycles::system_hatch_eggs,
-                life_cycles::system_duckling_to_juvenile,
-                life_cycles::system_juvenile_to_adult,
+                movement::system_interpolate_translation,
+                food::system_select_food_kind,
+                food::system_place_food_on_input.run_if(not(replay::is_replaying)),
+                scare::system_scare_on_input.run_if(not(replay::is_replaying)),
+                sprite_animation::system_animate_sprites,
                 cursor::system_update_game_cursor_position,
                 cursor::system_update_game_cursor_image,
-                game_state::system_update_remaining_time,
                 game_state::system_update_game_status_ui,
-                game_state::system_check_game_over_condition,
+                inspector::system_select_duck_on_input,
+                inspector::system_update_inspector_ui,
             )
                 .run_if(in_state(game_state::GameState::Running)),
         )
         .add_systems(
             Update,
             (
+                tuning::system_apply_boid_tuning,
+                director::system_apply_threat_director_config,
+                difficulty::system_apply_difficulty_presets,
+                terrain::system_apply_pond_map,
+            )
+                .run_if(not(replay::is_using_recorded_configs)),
+        )
+        .add_systems(
+            Update,
+            (
+                terrain::system_spawn_terrain_tiles,
                 ui::system_ui_actions,
+                ui::system_update_custom_difficulty_ui.after(ui::system_ui_actions),
                 ui::system_button_color,
-                leaderboard::system_display_leaderboard,
                 audio::system_update_active_audio_sources,
+                audio::system_play_gameplay_sounds,
+                vfx::system_spawn_gameplay_effects,
                 //vfx::spawn_particle_systems.run_if(input_just_pressed(MouseButton::Left)),
             ),
         )
+        // After the interpolation, so the camera follows the flock where it is drawn
+        .add_systems(
+            Update,
+            (
+                camera::system_camera_input,
+                camera::system_follow_flock,
+                camera::system_clamp_camera_to_world,
+            )
+                .chain()
+                .after(movement::system_interpolate_translation),
+        )
         .add_systems(
             OnEnter(game_state::GameState::Running),
             (
                 audio::system_start_soundtrack,
-                game_state::system_reset_remaining_time,
                 cursor::system_enable_game_cursor,
             ),
         )
@@ -184,7 +166,10 @@ fn main() {
 }
 
 fn setup(mut commands: Commands) {
-    commands.spawn(Camera2dBundle::default());
+    commands.spawn((
+        Camera2dBundle::default(),
+        camera::CameraController::default(),
+    ));
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -1,5 +1,8 @@
+use crate::genetics::Genome;
+use crate::terrain::PondMap;
+use crate::tuning::BoidTuning;
+use crate::{food, threat_boid};
 use bevy::prelude::*;
-use bevy::window::PrimaryWindow;
 
 #[derive(Component)]
 pub struct VelocityLimits {
@@ -18,54 +21,164 @@ impl Default for VelocityLimits {
 #[derive(Component, Default)]
 pub struct Velocity(pub Vec2);
 
-pub fn system_movement(time: Res<Time>, mut query: Query<(&Velocity, &mut Transform)>) {
-    for (velocity, mut transform) in query.iter_mut() {
-        transform.translation += velocity.0.extend(0.0) * time.delta_seconds();
+// Boids don't move into tiles they can't enter, they slide along them or turn around instead
+pub fn system_movement(
+    time: Res<Time>,
+    pond_map: Res<PondMap>,
+    mut query: Query<(&mut Velocity, &mut Transform, Option<&threat_boid::Threat>)>,
+) {
+    for (mut velocity, mut transform, threat) in query.iter_mut() {
+        let threat_kind = threat.map(|threat| threat.kind);
+        let position = transform.translation.xy();
+        let mut new_position = position + velocity.0 * time.delta_seconds();
+
+        // Something that got into an obstacle anyway, like by spawning on it, can walk out
+        if !pond_map.is_passable(new_position, threat_kind)
+            && pond_map.is_passable(position, threat_kind)
+        {
+            if pond_map.is_passable(Vec2::new(new_position.x, position.y), threat_kind) {
+                new_position.y = position.y;
+                velocity.0.y = 0.0;
+            } else if pond_map.is_passable(Vec2::new(position.x, new_position.y), threat_kind) {
+                new_position.x = position.x;
+                velocity.0.x = 0.0;
+            } else {
+                new_position = position;
+                velocity.0 = -velocity.0;
+            }
+        }
+
+        transform.translation.x = new_position.x;
+        transform.translation.y = new_position.y;
     }
 }
 
-pub fn system_clamp_velocity(mut query: Query<(&mut Velocity, &VelocityLimits)>) {
-    for (mut velocity, velocity_limits) in query.iter_mut() {
+// Simulated position before and after the last fixed step, rendering blends between the two
+#[derive(Component)]
+pub struct InterpolatedTranslation {
+    previous: Vec2,
+    current: Vec2,
+}
+
+// Undoes the rendering interpolation so the fixed step continues from the simulated position
+pub fn system_restore_simulated_translation(
+    mut query: Query<(&InterpolatedTranslation, &mut Transform)>,
+) {
+    for (interpolated_translation, mut transform) in query.iter_mut() {
+        transform.translation.x = interpolated_translation.current.x;
+        transform.translation.y = interpolated_translation.current.y;
+    }
+}
+
+pub fn system_store_simulated_translation(
+    mut commands: Commands,
+    mut query: Query<(&mut InterpolatedTranslation, &Transform)>,
+    new_query: Query<(Entity, &Transform), (With<Velocity>, Without<InterpolatedTranslation>)>,
+) {
+    for (mut interpolated_translation, transform) in query.iter_mut() {
+        interpolated_translation.previous = interpolated_translation.current;
+        interpolated_translation.current = transform.translation.xy();
+    }
+
+    for (entity, transform) in new_query.iter() {
+        commands.entity(entity).try_insert(InterpolatedTranslation {
+            previous: transform.translation.xy(),
+            current: transform.translation.xy(),
+        });
+    }
+}
+
+pub fn system_interpolate_translation(
+    fixed_time: Res<Time<Fixed>>,
+    mut query: Query<(&InterpolatedTranslation, &mut Transform)>,
+) {
+    let alpha = fixed_time.overstep_fraction();
+    for (interpolated_translation, mut transform) in query.iter_mut() {
+        let translation = interpolated_translation
+            .previous
+            .lerp(interpolated_translation.current, alpha);
+        transform.translation.x = translation.x;
+        transform.translation.y = translation.y;
+    }
+}
+
+pub fn system_clamp_velocity(
+    pond_map: Res<PondMap>,
+    mut query: Query<(
+        &mut Velocity,
+        &VelocityLimits,
+        &Transform,
+        Option<&food::FoodBoost>,
+        Option<&Genome>,
+        Option<&threat_boid::Threat>,
+    )>,
+) {
+    for (mut velocity, velocity_limits, transform, food_boost, genome, threat) in query.iter_mut() {
+        let terrain_factor = pond_map
+            .kind_at(transform.translation.xy())
+            .speed_factor(threat.map(|threat| threat.kind));
+        let max = velocity_limits.max
+            * food_boost.map_or(1.0, food::FoodBoost::speed_factor)
+            * genome.map_or(1.0, |genome| genome.speed)
+            * terrain_factor;
         velocity.0 = velocity
             .0
-            .clamp_length(velocity_limits.min, velocity_limits.max);
+            .clamp_length(velocity_limits.min, max.max(velocity_limits.min));
     }
 }
 
-const EDGE_MARGIN: f32 = 100.0;
-const TURN_FACTOR: f32 = 130.0;
+// The area the boids are kept in, centered on the origin. It doesn't change with the window,
+// the camera pans and zooms over it instead.
+#[derive(Resource)]
+pub struct WorldBounds {
+    pub size: Vec2,
+}
+impl Default for WorldBounds {
+    fn default() -> Self {
+        Self {
+            size: Vec2::new(1920.0, 1080.0),
+        }
+    }
+}
 
-pub fn is_avoiding_edge(position: Vec2, window: &Window) -> bool {
-    position.x < -window.width() * 0.5 + EDGE_MARGIN
-        || position.x > window.width() * 0.5 - EDGE_MARGIN
-        || position.y < -window.height() * 0.5 + EDGE_MARGIN
-        || position.y > window.height() * 0.5 - EDGE_MARGIN
+pub fn is_avoiding_edge(
+    position: Vec2,
+    world_bounds: &WorldBounds,
+    boid_tuning: &BoidTuning,
+) -> bool {
+    let edge_margin = boid_tuning.edge_margin;
+    position.x < -world_bounds.size.x * 0.5 + edge_margin
+        || position.x > world_bounds.size.x * 0.5 - edge_margin
+        || position.y < -world_bounds.size.y * 0.5 + edge_margin
+        || position.y > world_bounds.size.y * 0.5 - edge_margin
 }
 
 pub fn system_avoid_edges(
     time: Res<Time>,
-    mut query: Query<(&mut Velocity, &Transform)>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
+    boid_tuning: Res<BoidTuning>,
+    world_bounds: Res<WorldBounds>,
+    mut query: Query<(&mut Velocity, &Transform, Option<&threat_boid::Threat>)>,
 ) {
-    let window = if let Ok(window) = window_query.get_single() {
-        window
-    } else {
-        return;
-    };
-    let window_width = window.width();
-    let window_height = window.height();
-
-    for (mut velocity, transform) in query.iter_mut() {
-        if transform.translation.x < -window_width * 0.5 + EDGE_MARGIN {
-            velocity.0.x += TURN_FACTOR * time.delta_seconds();
-        } else if transform.translation.x > window_width * 0.5 - EDGE_MARGIN {
-            velocity.0.x -= TURN_FACTOR * time.delta_seconds();
+    let world_width = world_bounds.size.x;
+    let world_height = world_bounds.size.y;
+    let edge_margin = boid_tuning.edge_margin;
+    let turn_factor = boid_tuning.turn_factor;
+
+    for (mut velocity, transform, threat) in query.iter_mut() {
+        if threat.is_some_and(|threat| threat.state == threat_boid::ThreatState::Leave) {
+            continue;
+        }
+
+        if transform.translation.x < -world_width * 0.5 + edge_margin {
+            velocity.0.x += turn_factor * time.delta_seconds();
+        } else if transform.translation.x > world_width * 0.5 - edge_margin {
+            velocity.0.x -= turn_factor * time.delta_seconds();
         }
 
-        if transform.translation.y < -window_height * 0.5 + EDGE_MARGIN {
-            velocity.0.y += TURN_FACTOR * time.delta_seconds();
-        } else if transform.translation.y > window_height * 0.5 - EDGE_MARGIN {
-            velocity.0.y -= TURN_FACTOR * time.delta_seconds();
+        if transform.translation.y < -world_height * 0.5 + edge_margin {
+            velocity.0.y += turn_factor * time.delta_seconds();
+        } else if transform.translation.y > world_height * 0.5 - edge_margin {
+            velocity.0.y -= turn_factor * time.delta_seconds();
         }
     }
 }
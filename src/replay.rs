@@ -0,0 +1,427 @@
+// Records a session to a file and plays it back:
+//
+//     cargo run -- --record run.replay.ron
+//     cargo run -- --replay run.replay.ron
+//
+// A recording holds the startup seed, every food placement, every scare and every game state change,
+// each stamped with the simulation tick it happened on. It also holds the configs from
+// `assets/config` the session ran with, including the pond map, replays use those instead of the
+// files on disk. A saved round can't be continued while recording or replaying.
+// Everything else follows from the seed.
+
+use crate::difficulty::{CurrentDifficulty, Difficulty, DifficultyPresets};
+use crate::director::ThreatDirectorConfig;
+use crate::game_state::GameState;
+use crate::rng::SimulationRng;
+use crate::simulation::{LoadedConfigs, SimulationTick};
+use crate::terrain::PondMap;
+use crate::tuning::BoidTuning;
+use crate::{food, game_state, player, scare, simulation, ui};
+use bevy::app::RunFixedMainLoop;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RecordedFoodPlacement {
+    pub tick: u64,
+    pub position: (f32, f32),
+    #[serde(default)]
+    pub kind: food::FoodKind,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RecordedScare {
+    pub tick: u64,
+    pub position: (f32, f32),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RecordedStateChange {
+    pub tick: u64,
+    pub state: GameState,
+    pub is_simulating: bool,
+    #[serde(default)]
+    pub difficulty: Difficulty,
+}
+
+// The configs the simulation ran with from the tick after `tick` on. A new set gets recorded
+// whenever one of them is (re)loaded.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RecordedConfigs {
+    pub tick: u64,
+    pub boid_tuning: BoidTuning,
+    pub threat_director: ThreatDirectorConfig,
+    pub difficulty_presets: DifficultyPresets,
+    #[serde(default)]
+    pub pond_map: PondMap,
+}
+
+impl RecordedConfigs {
+    fn apply(
+        &self,
+        boid_tuning: &mut BoidTuning,
+        threat_director: &mut ThreatDirectorConfig,
+        difficulty_presets: &mut DifficultyPresets,
+        pond_map: &mut PondMap,
+        current_difficulty: &mut CurrentDifficulty,
+    ) {
+        *boid_tuning = self.boid_tuning.clone();
+        *threat_director = self.threat_director.clone();
+        *difficulty_presets = self.difficulty_presets.clone();
+        *pond_map = self.pond_map.clone();
+        let difficulty = current_difficulty.difficulty;
+        current_difficulty.select(difficulty, difficulty_presets);
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Recording {
+    pub seed: u64,
+    pub food_placements: Vec<RecordedFoodPlacement>,
+    #[serde(default)]
+    pub scares: Vec<RecordedScare>,
+    pub state_changes: Vec<RecordedStateChange>,
+    // Recordings without them replay with whatever is in `assets/config`
+    #[serde(default)]
+    pub configs: Vec<RecordedConfigs>,
+}
+
+#[derive(Resource)]
+pub struct Recorder {
+    path: PathBuf,
+    recording: Recording,
+}
+
+impl Recorder {
+    fn save(&self) {
+        let pretty_config = ron::ser::PrettyConfig::default();
+        let result = ron::ser::to_string_pretty(&self.recording, pretty_config)
+            .map_err(|error| error.to_string())
+            .and_then(|contents| {
+                std::fs::write(&self.path, contents).map_err(|error| error.to_string())
+            });
+        if let Err(error) = result {
+            error!("Could not save recording to {:?}: {error}", self.path);
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct Replay {
+    recording: Recording,
+    next_food_placement: usize,
+    next_scare: usize,
+    next_state_change: usize,
+    next_configs: usize,
+}
+
+// Set up from `--record <path>` and `--replay <path>` on the command line
+#[derive(Default)]
+pub struct ReplayPlugin {
+    record_path: Option<PathBuf>,
+    replay: Option<Recording>,
+}
+
+impl ReplayPlugin {
+    pub fn from_args() -> Self {
+        let mut replay_plugin = Self::default();
+        let mut args = std::env::args();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--record" => replay_plugin.record_path = args.next().map(PathBuf::from),
+                "--replay" => {
+                    let path = args.next().expect("Expected a path after --replay");
+                    let contents = std::fs::read_to_string(&path)
+                        .unwrap_or_else(|error| panic!("Could not read replay {path}: {error}"));
+                    let recording = ron::de::from_str(&contents)
+                        .unwrap_or_else(|error| panic!("Could not parse replay {path}: {error}"));
+                    replay_plugin.replay = Some(recording);
+                }
+                _ => {}
+            }
+        }
+        replay_plugin
+    }
+
+    // The seed the simulation has to start from for the replay to match
+    pub fn seed(&self) -> Option<u64> {
+        self.replay.as_ref().map(|recording| recording.seed)
+    }
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(path) = &self.record_path {
+            app.insert_resource(Recorder {
+                path: path.clone(),
+                recording: Recording::default(),
+            })
+            .add_systems(Startup, system_store_recording_seed)
+            // Recording and replaying stay out of `FixedUpdate`, adding systems there could change
+            // the order of the simulation's own systems and with it the outcome
+            .add_systems(
+                FixedFirst,
+                (system_record_food_placements, system_record_scares)
+                    .after(simulation::system_advance_simulation_tick)
+                    .run_if(game_state::is_simulation_running),
+            )
+            // Between the state transition and the fixed ticks of a frame, so the tick is the one
+            // the new state starts on
+            .add_systems(
+                RunFixedMainLoop,
+                (
+                    system_record_state_changes,
+                    system_record_configs.run_if(simulation::are_configs_loaded),
+                )
+                    .before(bevy::time::run_fixed_main_schedule),
+            )
+            .add_systems(Last, system_save_recording_on_exit);
+        }
+
+        if let Some(recording) = &self.replay {
+            // The first configs are in place before anything gets spawned
+            if let Some(configs) = recording.configs.first() {
+                let world = app.world_mut();
+                let mut boid_tuning = world.resource::<BoidTuning>().clone();
+                let mut threat_director = world.resource::<ThreatDirectorConfig>().clone();
+                let mut difficulty_presets = world.resource::<DifficultyPresets>().clone();
+                let mut pond_map = world.resource::<PondMap>().clone();
+                configs.apply(
+                    &mut boid_tuning,
+                    &mut threat_director,
+                    &mut difficulty_presets,
+                    &mut pond_map,
+                    &mut world.resource_mut::<CurrentDifficulty>(),
+                );
+                app.insert_resource(boid_tuning)
+                    .insert_resource(threat_director)
+                    .insert_resource(difficulty_presets)
+                    .insert_resource(pond_map)
+                    .insert_resource(LoadedConfigs::all());
+            }
+
+            app.insert_resource(Replay {
+                recording: recording.clone(),
+                next_food_placement: 0,
+                next_scare: 0,
+                next_state_change: 0,
+                next_configs: 1,
+            })
+            .add_systems(
+                FixedFirst,
+                (
+                    system_replay_configs,
+                    system_replay_food_placements,
+                    system_replay_scares,
+                )
+                    .after(simulation::system_advance_simulation_tick)
+                    .run_if(game_state::is_simulation_running),
+            )
+            .add_systems(
+                PreUpdate,
+                system_replay_state_changes.run_if(simulation::are_configs_loaded),
+            );
+        }
+    }
+}
+
+pub fn is_replaying(replay: Option<Res<Replay>>) -> bool {
+    replay.is_some()
+}
+
+// The files in `assets/config` are left alone then, even when they get hot reloaded
+pub fn is_using_recorded_configs(replay: Option<Res<Replay>>) -> bool {
+    replay.is_some_and(|replay| !replay.recording.configs.is_empty())
+}
+
+fn system_store_recording_seed(mut recorder: ResMut<Recorder>, rng: Res<SimulationRng>) {
+    recorder.recording.seed = rng.seed();
+}
+
+fn system_record_food_placements(
+    mut recorder: ResMut<Recorder>,
+    simulation_tick: Res<SimulationTick>,
+    food_placement_queue: Res<food::FoodPlacementQueue>,
+) {
+    for (position, kind) in food_placement_queue.0.iter() {
+        recorder
+            .recording
+            .food_placements
+            .push(RecordedFoodPlacement {
+                tick: simulation_tick.0,
+                position: (position.x, position.y),
+                kind: *kind,
+            });
+    }
+}
+
+fn system_record_scares(
+    mut recorder: ResMut<Recorder>,
+    simulation_tick: Res<SimulationTick>,
+    scare_queue: Res<scare::ScareQueue>,
+) {
+    for position in scare_queue.0.iter() {
+        recorder.recording.scares.push(RecordedScare {
+            tick: simulation_tick.0,
+            position: (position.x, position.y),
+        });
+    }
+}
+
+fn system_record_state_changes(
+    mut recorder: ResMut<Recorder>,
+    mut state_transitions: EventReader<StateTransitionEvent<GameState>>,
+    simulation_tick: Res<SimulationTick>,
+    player_stats: Res<player::PlayerStats>,
+    current_difficulty: Res<CurrentDifficulty>,
+) {
+    let mut changed = false;
+    for transition in state_transitions.read() {
+        let state = if let Some(state) = transition.entered {
+            state
+        } else {
+            continue;
+        };
+        recorder.recording.state_changes.push(RecordedStateChange {
+            tick: simulation_tick.0,
+            state,
+            is_simulating: player_stats.is_simulating,
+            difficulty: current_difficulty.difficulty,
+        });
+        changed = true;
+    }
+
+    // Saved as it goes, so the recording survives the game being killed
+    if changed {
+        recorder.save();
+    }
+}
+
+fn system_record_configs(
+    mut recorder: ResMut<Recorder>,
+    simulation_tick: Res<SimulationTick>,
+    boid_tuning: Res<BoidTuning>,
+    threat_director: Res<ThreatDirectorConfig>,
+    difficulty_presets: Res<DifficultyPresets>,
+    pond_map: Res<PondMap>,
+) {
+    if !boid_tuning.is_changed()
+        && !threat_director.is_changed()
+        && !difficulty_presets.is_changed()
+        && !pond_map.is_changed()
+    {
+        return;
+    }
+
+    recorder.recording.configs.push(RecordedConfigs {
+        tick: simulation_tick.0,
+        boid_tuning: boid_tuning.clone(),
+        threat_director: threat_director.clone(),
+        difficulty_presets: difficulty_presets.clone(),
+        pond_map: pond_map.clone(),
+    });
+    recorder.save();
+}
+
+fn system_save_recording_on_exit(mut app_exit: EventReader<AppExit>, recorder: Res<Recorder>) {
+    if app_exit.read().next().is_some() {
+        recorder.save();
+    }
+}
+
+fn system_replay_configs(
+    mut replay: ResMut<Replay>,
+    simulation_tick: Res<SimulationTick>,
+    mut boid_tuning: ResMut<BoidTuning>,
+    mut threat_director: ResMut<ThreatDirectorConfig>,
+    mut difficulty_presets: ResMut<DifficultyPresets>,
+    mut pond_map: ResMut<PondMap>,
+    mut current_difficulty: ResMut<CurrentDifficulty>,
+) {
+    while let Some(configs) = replay.recording.configs.get(replay.next_configs) {
+        if configs.tick >= simulation_tick.0 {
+            break;
+        }
+        configs.apply(
+            &mut boid_tuning,
+            &mut threat_director,
+            &mut difficulty_presets,
+            &mut pond_map,
+            &mut current_difficulty,
+        );
+        replay.next_configs += 1;
+    }
+}
+
+fn system_replay_food_placements(
+    mut replay: ResMut<Replay>,
+    simulation_tick: Res<SimulationTick>,
+    mut food_placement_queue: ResMut<food::FoodPlacementQueue>,
+) {
+    while let Some(food_placement) = replay
+        .recording
+        .food_placements
+        .get(replay.next_food_placement)
+    {
+        if food_placement.tick > simulation_tick.0 {
+            break;
+        }
+        let (x, y) = food_placement.position;
+        food_placement_queue
+            .0
+            .push((Vec2::new(x, y), food_placement.kind));
+        replay.next_food_placement += 1;
+    }
+}
+
+fn system_replay_scares(
+    mut replay: ResMut<Replay>,
+    simulation_tick: Res<SimulationTick>,
+    mut scare_queue: ResMut<scare::ScareQueue>,
+) {
+    while let Some(scare) = replay.recording.scares.get(replay.next_scare) {
+        if scare.tick > simulation_tick.0 {
+            break;
+        }
+        let (x, y) = scare.position;
+        scare_queue.0.push(Vec2::new(x, y));
+        replay.next_scare += 1;
+    }
+}
+
+// Runs before the state transition of a frame. State changes the game makes on its own
+// (e.g. running out of time) are already pending by then, so only the player's get applied here.
+fn system_replay_state_changes(
+    mut commands: Commands,
+    mut replay: ResMut<Replay>,
+    simulation_tick: Res<SimulationTick>,
+    game_state: Res<State<GameState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut player_stats: ResMut<player::PlayerStats>,
+    mut current_difficulty: ResMut<CurrentDifficulty>,
+    difficulty_presets: Res<DifficultyPresets>,
+    menu_screen_query: Query<Entity, With<ui::OnMenuScreen>>,
+) {
+    while let Some(state_change) = replay.recording.state_changes.get(replay.next_state_change) {
+        if state_change.tick > simulation_tick.0 {
+            return;
+        }
+        if state_change.state == *game_state.get() {
+            replay.next_state_change += 1;
+            continue;
+        }
+        if matches!(*next_game_state, NextState::Pending(_)) {
+            return;
+        }
+
+        player_stats.is_simulating = state_change.is_simulating;
+        current_difficulty.select(state_change.difficulty, &difficulty_presets);
+        next_game_state.set(state_change.state);
+        // Close the menus like the button the player pressed did
+        for entity in menu_screen_query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+}
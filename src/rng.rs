@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+
+// Where a run is in its random numbers, enough to pick it up again exactly
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct RngState {
+    pub seed: u64,
+    // Words drawn since seeding
+    pub word_pos: u64,
+}
+
+// Single source of randomness for the simulation, so a run can be replayed from its seed.
+// The same generator as `StdRng`, but its position can be saved.
+#[derive(Resource)]
+pub struct SimulationRng {
+    seed: u64,
+    rng: ChaCha12Rng,
+}
+
+impl SimulationRng {
+    pub fn from_seed(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: ChaCha12Rng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn from_state(state: RngState) -> Self {
+        let mut simulation_rng = Self::from_seed(state.seed);
+        simulation_rng.rng.set_word_pos(state.word_pos as u128);
+        simulation_rng
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn state(&self) -> RngState {
+        RngState {
+            seed: self.seed,
+            word_pos: self.rng.get_word_pos() as u64,
+        }
+    }
+
+    // The next seed is drawn from the current one, so a whole session follows from the startup seed
+    pub fn reseed(&mut self) {
+        let seed = self.rng.gen();
+        *self = Self::from_seed(seed);
+    }
+}
+
+impl RngCore for SimulationRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+// Uses `--seed <number>` from the command line, or a random seed if none was given
+pub fn startup_seed() -> u64 {
+    let mut args = std::env::args();
+    while let Some(arg) = args.next() {
+        if arg != "--seed" {
+            continue;
+        }
+        if let Some(seed) = args.next().and_then(|seed| seed.parse().ok()) {
+            return seed;
+        }
+        warn!("Expected a number after --seed, using a random seed instead");
+    }
+    rand::random()
+}
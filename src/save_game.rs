@@ -0,0 +1,529 @@
+use crate::breeding::{BreedingProgress, Relationship, Sambo};
+use crate::difficulty::{CurrentDifficulty, Difficulty, DifficultyPresets, DifficultySettings};
+use crate::director::{IncomingThreat, ThreatDirector, ThreatDirectorConfig};
+use crate::food::{Food, FoodBoost, FoodKind};
+use crate::game_state::{GameState, RemoveOnRestart};
+use crate::genetics::Genome;
+use crate::hunger::Hunger;
+use crate::life_cycles::{Adult, Duckling, Egg, Elder, Juvenile, LifeCycleTime, LifeStage};
+use crate::lineage::{Ancestry, FamilyTree};
+use crate::movement::Velocity;
+use crate::player::PlayerStats;
+use crate::rng::{RngState, SimulationRng};
+use crate::spawning::LoadedAssets;
+use crate::sprite_animation::AnimationTimer;
+use crate::threat_boid::{Threat, ThreatKind, ThreatState};
+use crate::tuning::BoidTuning;
+use crate::{game_state, replay, spawning};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+const SAVE_GAME_PATH: &str = "savegame.ron";
+const AUTOSAVE_INTERVAL: f32 = 30.0;
+
+#[derive(Serialize, Deserialize)]
+struct SavedDuck {
+    stage: LifeStage,
+    position: (f32, f32),
+    velocity: (f32, f32),
+    life_cycle_time: f32,
+    #[serde(default)]
+    hunger: f32,
+    #[serde(default)]
+    food_boost: Option<(FoodKind, f32)>,
+    #[serde(default)]
+    genome: Genome,
+    // Saves from before the family tree start new lines
+    #[serde(default)]
+    ancestry: Option<Ancestry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedThreat {
+    position: (f32, f32),
+    velocity: (f32, f32),
+    #[serde(default)]
+    kind: ThreatKind,
+    #[serde(default)]
+    home: (f32, f32),
+    #[serde(default)]
+    state: ThreatState,
+    #[serde(default)]
+    state_timer: f32,
+    #[serde(default)]
+    wander_direction: (f32, f32),
+    #[serde(default)]
+    flee_from: (f32, f32),
+    #[serde(default)]
+    meals: u32,
+    #[serde(default)]
+    scares: u32,
+    cat_variation: Option<usize>,
+    #[serde(default)]
+    animation_index: usize,
+    #[serde(default = "default_animation_frame_duration")]
+    animation_frame_duration: f32,
+}
+
+// Saves from before the animation was saved
+fn default_animation_frame_duration() -> f32 {
+    1.25
+}
+
+// Partners are indices into `SaveGame::ducks`
+#[derive(Serialize, Deserialize)]
+struct SavedRelationship {
+    partner1: usize,
+    partner2: usize,
+    duration: f32,
+    breeding_progress: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedFood {
+    position: (f32, f32),
+    rotation: f32,
+    #[serde(default)]
+    kind: FoodKind,
+    #[serde(default)]
+    age: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveGame {
+    score: i32,
+    ducks_born: i32,
+    is_simulating: bool,
+    remaining_time: f32,
+    #[serde(default)]
+    difficulty: Difficulty,
+    // The custom difficulty can be changed in the menu, so its settings are kept with the round
+    #[serde(default)]
+    difficulty_settings: Option<DifficultySettings>,
+    // Seconds into the round, for the threat director
+    #[serde(default)]
+    round_time: f32,
+    // Threats of a wave that hadn't all come in yet
+    #[serde(default)]
+    incoming_threats: Vec<IncomingThreat>,
+    #[serde(default)]
+    family_tree: FamilyTree,
+    // Saves from before it was saved go on with the RNG of the current session
+    #[serde(default)]
+    rng: Option<RngState>,
+    ducks: Vec<SavedDuck>,
+    threats: Vec<SavedThreat>,
+    relationships: Vec<SavedRelationship>,
+    food: Vec<SavedFood>,
+}
+
+// Sent by the "Continue" button, the saved pond replaces the current one when the round starts
+#[derive(Event)]
+pub struct ContinueSavedGame;
+
+// Only a round that was started (or continued) gets saved, and only until it ends
+#[derive(Resource)]
+struct Autosave {
+    timer: Timer,
+    active: bool,
+}
+
+pub struct SaveGamePlugin;
+
+impl Plugin for SaveGamePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Autosave {
+            timer: Timer::from_seconds(AUTOSAVE_INTERVAL, TimerMode::Repeating),
+            active: false,
+        })
+        .add_event::<ContinueSavedGame>()
+        // A replayed round is never saved, and doesn't touch the save of the player's own round
+        // either. `Autosave::active` stays false while replaying.
+        .add_systems(
+            OnEnter(GameState::Running),
+            system_load_save_game
+                .after(game_state::system_reset_remaining_time)
+                .run_if(not(replay::is_replaying)),
+        )
+        .add_systems(
+            OnEnter(GameState::TimeOver),
+            system_delete_save_game.run_if(not(replay::is_replaying)),
+        )
+        .add_systems(
+            OnEnter(GameState::GameOver),
+            system_delete_save_game.run_if(not(replay::is_replaying)),
+        )
+        .add_systems(
+            Update,
+            system_autosave
+                .run_if(in_state(GameState::Running))
+                .run_if(not(replay::is_replaying)),
+        )
+        .add_systems(
+            Last,
+            system_save_game_on_exit.run_if(not(replay::is_replaying)),
+        );
+    }
+}
+
+pub fn has_save_game() -> bool {
+    cfg!(not(target_arch = "wasm32")) && std::path::Path::new(SAVE_GAME_PATH).exists()
+}
+
+fn vec2_to_tuple(vec: Vec2) -> (f32, f32) {
+    (vec.x, vec.y)
+}
+
+fn tuple_to_vec2((x, y): (f32, f32)) -> Vec2 {
+    Vec2::new(x, y)
+}
+
+fn save_game(save_game: &SaveGame) {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let result = ron::ser::to_string_pretty(save_game, ron::ser::PrettyConfig::default())
+            .map_err(|error| error.to_string())
+            .and_then(|contents| {
+                std::fs::write(SAVE_GAME_PATH, contents).map_err(|error| error.to_string())
+            });
+        if let Err(error) = result {
+            warn!("Could not save the game to {SAVE_GAME_PATH}: {error}");
+        }
+    }
+    #[cfg(target_arch = "wasm32")]
+    let _ = save_game;
+}
+
+fn load_save_game() -> Option<SaveGame> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let contents = std::fs::read_to_string(SAVE_GAME_PATH).ok()?;
+        match ron::de::from_str(&contents) {
+            Ok(save_game) => return Some(save_game),
+            Err(error) => warn!("Could not parse {SAVE_GAME_PATH}: {error}"),
+        }
+    }
+    None
+}
+
+// Everything that ends up in a save game
+#[derive(SystemParam)]
+struct PondQuery<'w, 's> {
+    player_stats: Res<'w, PlayerStats>,
+    current_difficulty: Res<'w, CurrentDifficulty>,
+    threat_director: Res<'w, ThreatDirector>,
+    family_tree: Res<'w, FamilyTree>,
+    rng: Res<'w, SimulationRng>,
+    duck_query: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Transform,
+            &'static LifeCycleTime,
+            Option<&'static Velocity>,
+            Option<&'static Hunger>,
+            Option<&'static FoodBoost>,
+            Option<&'static Genome>,
+            Option<&'static Ancestry>,
+            Has<Duckling>,
+            Has<Juvenile>,
+            Has<Adult>,
+            Has<Elder>,
+        ),
+        Or<(With<Egg>, With<Duckling>, With<Juvenile>, With<Adult>)>,
+    >,
+    threat_query: Query<
+        'w,
+        's,
+        (
+            &'static Transform,
+            &'static Velocity,
+            &'static Threat,
+            &'static TextureAtlas,
+            &'static AnimationTimer,
+        ),
+    >,
+    relationship_query: Query<'w, 's, (&'static Relationship, &'static BreedingProgress)>,
+    food_query: Query<'w, 's, (&'static Transform, &'static Food)>,
+}
+
+impl PondQuery<'_, '_> {
+    fn collect(&self) -> SaveGame {
+        let mut duck_indices: HashMap<Entity, usize> = Default::default();
+        let mut ducks = Vec::new();
+        for (
+            entity,
+            transform,
+            life_cycle_time,
+            velocity,
+            hunger,
+            food_boost,
+            genome,
+            ancestry,
+            is_duckling,
+            is_juvenile,
+            is_adult,
+            is_elder,
+        ) in self.duck_query.iter()
+        {
+            let stage = if is_elder {
+                LifeStage::Elder
+            } else if is_adult {
+                LifeStage::Adult
+            } else if is_juvenile {
+                LifeStage::Juvenile
+            } else if is_duckling {
+                LifeStage::Duckling
+            } else {
+                LifeStage::Egg
+            };
+
+            duck_indices.insert(entity, ducks.len());
+            ducks.push(SavedDuck {
+                stage,
+                position: vec2_to_tuple(transform.translation.xy()),
+                velocity: vec2_to_tuple(velocity.map_or(Vec2::ZERO, |velocity| velocity.0)),
+                life_cycle_time: life_cycle_time.0,
+                hunger: hunger.map_or(0.0, |hunger| hunger.0),
+                food_boost: food_boost
+                    .map(|food_boost| (food_boost.kind, food_boost.remaining_time)),
+                genome: genome.copied().unwrap_or_default(),
+                ancestry: ancestry.copied(),
+            });
+        }
+
+        let threats = self
+            .threat_query
+            .iter()
+            .map(
+                |(transform, velocity, threat, texture_atlas, animation_timer)| SavedThreat {
+                    position: vec2_to_tuple(transform.translation.xy()),
+                    velocity: vec2_to_tuple(velocity.0),
+                    kind: threat.kind,
+                    home: vec2_to_tuple(threat.home),
+                    state: threat.state,
+                    state_timer: threat.state_timer,
+                    wander_direction: vec2_to_tuple(threat.wander_direction),
+                    flee_from: vec2_to_tuple(threat.flee_from),
+                    meals: threat.meals,
+                    scares: threat.scares,
+                    cat_variation: threat.cat_variation,
+                    animation_index: texture_atlas.index,
+                    animation_frame_duration: animation_timer.0.duration().as_secs_f32(),
+                },
+            )
+            .collect();
+
+        let relationships = self
+            .relationship_query
+            .iter()
+            .filter_map(|(relationship, breeding_progress)| {
+                Some(SavedRelationship {
+                    partner1: *duck_indices.get(&relationship.partner1)?,
+                    partner2: *duck_indices.get(&relationship.partner2)?,
+                    duration: relationship.duration,
+                    breeding_progress: breeding_progress.0,
+                })
+            })
+            .collect();
+
+        let food = self
+            .food_query
+            .iter()
+            .map(|(transform, food)| SavedFood {
+                position: vec2_to_tuple(transform.translation.xy()),
+                rotation: transform.rotation.to_euler(EulerRot::ZYX).0,
+                kind: food.kind,
+                age: food.age,
+            })
+            .collect();
+
+        SaveGame {
+            score: self.player_stats.score,
+            ducks_born: self.player_stats.ducks_born,
+            is_simulating: self.player_stats.is_simulating,
+            remaining_time: self.player_stats.remaining_time,
+            difficulty: self.current_difficulty.difficulty,
+            difficulty_settings: Some(self.current_difficulty.settings.clone()),
+            round_time: self.threat_director.round_time,
+            incoming_threats: self.threat_director.incoming().to_vec(),
+            family_tree: self.family_tree.clone(),
+            rng: Some(self.rng.state()),
+            ducks,
+            threats,
+            relationships,
+            food,
+        }
+    }
+}
+
+fn system_autosave(time: Res<Time>, mut autosave: ResMut<Autosave>, pond_query: PondQuery) {
+    if !autosave.active || !autosave.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    save_game(&pond_query.collect());
+}
+
+fn system_save_game_on_exit(
+    mut app_exit_events: EventReader<AppExit>,
+    autosave: Res<Autosave>,
+    pond_query: PondQuery,
+) {
+    if app_exit_events.read().next().is_none() || !autosave.active {
+        return;
+    }
+    save_game(&pond_query.collect());
+}
+
+fn system_delete_save_game(mut autosave: ResMut<Autosave>) {
+    autosave.active = false;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Err(error) = std::fs::remove_file(SAVE_GAME_PATH) {
+        if error.kind() != std::io::ErrorKind::NotFound {
+            warn!("Could not delete {SAVE_GAME_PATH}: {error}");
+        }
+    }
+}
+
+fn system_load_save_game(
+    mut commands: Commands,
+    mut continue_events: EventReader<ContinueSavedGame>,
+    mut autosave: ResMut<Autosave>,
+    mut player_stats: ResMut<PlayerStats>,
+    mut current_difficulty: ResMut<CurrentDifficulty>,
+    difficulty_presets: Res<DifficultyPresets>,
+    mut threat_director: ResMut<ThreatDirector>,
+    threat_director_config: Res<ThreatDirectorConfig>,
+    mut family_tree: ResMut<FamilyTree>,
+    mut rng: ResMut<SimulationRng>,
+    loaded_assets: Res<LoadedAssets>,
+    boid_tuning: Res<BoidTuning>,
+    remove_on_restart_query: Query<Entity, With<RemoveOnRestart>>,
+) {
+    autosave.active = true;
+    autosave.timer.reset();
+
+    if continue_events.read().next().is_none() {
+        return;
+    }
+    let save_game = if let Some(save_game) = load_save_game() {
+        save_game
+    } else {
+        return;
+    };
+
+    for entity in remove_on_restart_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    player_stats.score = save_game.score;
+    player_stats.ducks_born = save_game.ducks_born;
+    player_stats.is_simulating = save_game.is_simulating;
+    player_stats.remaining_time = save_game.remaining_time;
+    current_difficulty.select(save_game.difficulty, &difficulty_presets);
+    if let Some(difficulty_settings) = save_game.difficulty_settings {
+        current_difficulty.settings = difficulty_settings;
+    }
+    *threat_director = ThreatDirector::resume_at(
+        save_game.round_time,
+        save_game.incoming_threats,
+        &threat_director_config,
+    );
+    *family_tree = save_game.family_tree;
+    // Picks up the random numbers where the saved round left off, so the end screen shows its seed
+    if let Some(rng_state) = save_game.rng {
+        *rng = SimulationRng::from_state(rng_state);
+    }
+
+    let duck_entities: Vec<Entity> = save_game
+        .ducks
+        .iter()
+        .map(|duck| {
+            let entity = spawning::spawn_duck(
+                tuple_to_vec2(duck.position),
+                duck.stage,
+                duck.life_cycle_time,
+                duck.hunger,
+                duck.genome,
+                duck.ancestry.unwrap_or_else(|| family_tree.add_founder()),
+                tuple_to_vec2(duck.velocity),
+                &mut commands,
+                &loaded_assets,
+                &boid_tuning,
+            );
+            if let Some((kind, remaining_time)) = duck.food_boost {
+                commands.entity(entity).insert(FoodBoost {
+                    kind,
+                    remaining_time,
+                });
+            }
+            entity
+        })
+        .collect();
+
+    for threat in save_game.threats.iter() {
+        spawning::spawn_threat_with(
+            tuple_to_vec2(threat.position),
+            Threat {
+                kind: threat.kind,
+                home: tuple_to_vec2(threat.home),
+                state: threat.state,
+                state_timer: threat.state_timer,
+                wander_direction: tuple_to_vec2(threat.wander_direction),
+                flee_from: tuple_to_vec2(threat.flee_from),
+                meals: threat.meals,
+                scares: threat.scares,
+                cat_variation: threat.cat_variation,
+                ..Default::default()
+            },
+            tuple_to_vec2(threat.velocity),
+            threat.animation_index,
+            threat.animation_frame_duration,
+            &mut commands,
+            &loaded_assets,
+            &current_difficulty.settings,
+        );
+    }
+
+    for relationship in save_game.relationships.iter() {
+        let (partner1, partner2) = if let (Some(partner1), Some(partner2)) = (
+            duck_entities.get(relationship.partner1),
+            duck_entities.get(relationship.partner2),
+        ) {
+            (*partner1, *partner2)
+        } else {
+            continue;
+        };
+
+        let relationship_entity = commands
+            .spawn(Relationship {
+                partner1,
+                partner2,
+                duration: relationship.duration,
+            })
+            .insert(BreedingProgress(relationship.breeding_progress))
+            .insert(RemoveOnRestart)
+            .id();
+        commands.entity(partner1).insert(Sambo {
+            relationship_entity,
+        });
+        commands.entity(partner2).insert(Sambo {
+            relationship_entity,
+        });
+    }
+
+    for food in save_game.food.iter() {
+        spawning::spawn_food_with(
+            tuple_to_vec2(food.position),
+            food.kind,
+            food.age,
+            food.rotation,
+            &mut commands,
+            &loaded_assets,
+        );
+    }
+}
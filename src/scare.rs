@@ -0,0 +1,112 @@
+use crate::threat_boid::{Threat, ThreatState};
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+// A clap at the cursor that sends nearby threats running
+#[derive(Event)]
+pub struct ThreatsScared {
+    pub position: Vec2,
+}
+
+const SCARE_COOLDOWN: f32 = 5.0;
+const SCARE_RADIUS: f32 = 150.0;
+// How long the cursor shows the clap after scaring
+const SCARE_CURSOR_DURATION: f32 = 0.3;
+
+#[derive(Resource)]
+pub struct ScareTimer(pub Timer);
+
+impl Default for ScareTimer {
+    fn default() -> Self {
+        let mut timer = Timer::from_seconds(SCARE_COOLDOWN, TimerMode::Once);
+        // Ready right away
+        timer.tick(timer.duration());
+        Self(timer)
+    }
+}
+
+impl ScareTimer {
+    pub fn is_ready(&self) -> bool {
+        self.0.finished()
+    }
+
+    pub fn just_scared(&self) -> bool {
+        !self.0.finished() && self.0.elapsed_secs() < SCARE_CURSOR_DURATION
+    }
+}
+
+// Scares by the player, applied on the next fixed tick so they can be recorded and replayed
+#[derive(Resource, Default)]
+pub struct ScareQueue(pub Vec<Vec2>);
+
+pub fn system_scare_on_input(
+    time: Res<Time>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    mouse_button_input: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut scare_timer: ResMut<ScareTimer>,
+    mut scare_queue: ResMut<ScareQueue>,
+) {
+    scare_timer.0.tick(time.delta());
+
+    let window = if let Ok(window) = window_query.get_single() {
+        window
+    } else {
+        return;
+    };
+
+    let cursor_position = if let Some(position) = window.cursor_position() {
+        position
+    } else {
+        return;
+    };
+
+    let (camera, camera_transform) =
+        if let Ok((camera, camera_transform)) = camera_query.get_single() {
+            (camera, camera_transform)
+        } else {
+            return;
+        };
+
+    if !scare_timer.is_ready() {
+        return;
+    }
+
+    if !mouse_button_input.just_pressed(MouseButton::Right)
+        && !keyboard_input.just_pressed(KeyCode::Space)
+    {
+        return;
+    }
+
+    let world_position = if let Some(world_position) =
+        camera.viewport_to_world_2d(camera_transform, cursor_position)
+    {
+        world_position
+    } else {
+        return;
+    };
+
+    scare_timer.0.reset();
+    scare_queue.0.push(world_position);
+}
+
+pub fn system_apply_scares(
+    mut scare_queue: ResMut<ScareQueue>,
+    mut threat_query: Query<(&Transform, &mut Threat)>,
+    mut threats_scared_events: EventWriter<ThreatsScared>,
+) {
+    for position in scare_queue.0.drain(..) {
+        for (threat_transform, mut threat) in threat_query.iter_mut() {
+            if threat.state == ThreatState::Leave {
+                continue;
+            }
+            if threat_transform.translation.xy().distance_squared(position)
+                < SCARE_RADIUS * SCARE_RADIUS
+            {
+                threat.scare(position);
+            }
+        }
+        threats_scared_events.send(ThreatsScared { position });
+    }
+}
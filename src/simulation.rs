@@ -0,0 +1,171 @@
+use crate::{
+    boid, breeding, difficulty, director, duck_boid, food, game_state, hunger, life_cycles,
+    lineage, movement, player, rng, scare, spatial_grid, spawning, terrain, threat_boid, tuning,
+};
+use bevy::prelude::*;
+
+// Number of fixed ticks simulated so far, counting the current one, used to timestamp recorded inputs
+#[derive(Resource, Default)]
+pub struct SimulationTick(pub u64);
+
+pub fn system_advance_simulation_tick(mut simulation_tick: ResMut<SimulationTick>) {
+    simulation_tick.0 += 1;
+}
+
+// Which of the configs in `assets/config` made it into their resources. A config that failed to
+// load counts too, its defaults are used then.
+#[derive(Resource, Default)]
+pub struct LoadedConfigs {
+    pub boid_tuning: bool,
+    pub threat_director: bool,
+    pub difficulty_presets: bool,
+    pub pond_map: bool,
+}
+
+impl LoadedConfigs {
+    // For configs that are set up before the app runs, like in the `sim` binary
+    pub fn all() -> Self {
+        Self {
+            boid_tuning: true,
+            threat_director: true,
+            difficulty_presets: true,
+            pond_map: true,
+        }
+    }
+
+    pub fn is_all_loaded(&self) -> bool {
+        self.boid_tuning && self.threat_director && self.difficulty_presets && self.pond_map
+    }
+}
+
+// Nothing gets spawned, simulated or replayed before, the configs would swap in partway through
+// otherwise. Ducks and threats could also be spawned on tiles they can't be on.
+pub fn are_configs_loaded(loaded_configs: Res<LoadedConfigs>) -> bool {
+    loaded_configs.is_all_loaded()
+}
+
+// Gameplay systems and resources, without any window, rendering or audio.
+// Shared by the game and the headless `sim` binary.
+pub struct SimulationPlugin {
+    pub seed: u64,
+}
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(spawning::LoadedAssets::default())
+            .insert_resource(spawning::CurrentThreats::default())
+            .insert_resource(player::PlayerStats::default())
+            .insert_resource(lineage::FamilyTree::default())
+            .insert_resource(rng::SimulationRng::from_seed(self.seed))
+            .insert_resource(spatial_grid::SpatialGrid::default())
+            .insert_resource(spatial_grid::EggSpatialGrid::default())
+            .insert_resource(tuning::BoidTuning::default())
+            .insert_resource(director::ThreatDirectorConfig::default())
+            .insert_resource(difficulty::DifficultyPresets::default())
+            .insert_resource(difficulty::CurrentDifficulty::default())
+            .insert_resource(director::ThreatDirector::default())
+            .insert_resource(movement::WorldBounds::default())
+            .insert_resource(terrain::PondMap::default())
+            .insert_resource(food::FoodPlacementQueue::default())
+            .insert_resource(scare::ScareQueue::default())
+            .insert_resource(SimulationTick::default())
+            .insert_resource(LoadedConfigs::default())
+            .insert_state(game_state::GameState::Paused)
+            .add_event::<threat_boid::DuckEaten>()
+            .add_event::<threat_boid::EggEaten>()
+            .add_event::<duck_boid::FoodEaten>()
+            .add_event::<life_cycles::EggHatched>()
+            .add_event::<life_cycles::LifeCycleTransitioned>()
+            .add_event::<life_cycles::DuckDiedOfOldAge>()
+            .add_event::<hunger::DuckStarved>()
+            .add_event::<food::FoodRotted>()
+            .add_event::<scare::ThreatsScared>()
+            .add_event::<director::ThreatWaveWarning>()
+            // The pond map has to be there to spawn everything on passable tiles
+            .add_systems(
+                PreUpdate,
+                (spawning::system_spawn_boids, spawning::system_spawn_threats)
+                    .chain()
+                    .run_if(are_configs_loaded.and_then(run_once())),
+            )
+            .add_systems(
+                OnEnter(game_state::GameState::Restarting),
+                game_state::system_restart_game,
+            )
+            .add_systems(
+                OnExit(game_state::GameState::Restarting),
+                (spawning::system_spawn_boids, spawning::system_spawn_threats).chain(),
+            )
+            .add_systems(
+                OnEnter(game_state::GameState::Running),
+                (
+                    game_state::system_reset_remaining_time,
+                    difficulty::system_apply_threat_speed,
+                ),
+            )
+            .add_systems(
+                FixedPreUpdate,
+                (
+                    spatial_grid::system_update_spatial_grid,
+                    spatial_grid::system_update_egg_spatial_grid,
+                )
+                    .run_if(game_state::is_simulation_running),
+            )
+            // Everything in `FixedUpdate` runs in one fixed order. Left to the scheduler, the order
+            // can change between runs and replays would drift apart.
+            .add_systems(
+                FixedUpdate,
+                (
+                    director::system_threat_director,
+                    breeding::system_build_relationships,
+                    breeding::system_breeding,
+                    boid::system_boid_separation,
+                    boid::system_boid_alignment_and_cohesion,
+                    duck_boid::system_boid_update_close_adults,
+                    duck_boid::system_boids_food,
+                    duck_boid::system_boid_mating_attraction,
+                    duck_boid::system_boids_ducklings_towards_adults,
+                    duck_boid::system_boids_avoid_threat,
+                    duck_boid::system_adults_mob_threats,
+                    threat_boid::system_threats_flee_from_mobs,
+                    threat_boid::system_threat_hunting,
+                    movement::system_clamp_velocity,
+                    threat_boid::system_update_threat_animation,
+                    movement::system_flip_based_on_velocity,
+                    movement::system_avoid_edges,
+                    terrain::system_avoid_obstacles,
+                    movement::system_movement,
+                    breeding::system_update_relationships,
+                )
+                    .chain()
+                    .run_if(game_state::is_simulation_running),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    food::system_apply_food_placements,
+                    scare::system_apply_scares,
+                    food::system_spoil_food,
+                    life_cycles::system_decrease_lifecycle_time,
+                    hunger::system_increase_hunger,
+                    hunger::system_starvation,
+                    food::system_update_food_boosts,
+                    life_cycles::system_hatch_eggs,
+                    life_cycles::system_duckling_to_juvenile,
+                    life_cycles::system_juvenile_to_adult,
+                    life_cycles::system_adult_to_elder,
+                    life_cycles::system_elder_death,
+                    game_state::system_update_remaining_time,
+                    game_state::system_check_game_over_condition,
+                )
+                    .chain()
+                    .after(breeding::system_update_relationships)
+                    .run_if(game_state::is_simulation_running),
+            )
+            .add_systems(
+                FixedFirst,
+                system_advance_simulation_tick.run_if(game_state::is_simulation_running),
+            )
+            .add_systems(Update, life_cycles::system_refresh_boid_profiles);
+    }
+}
@@ -0,0 +1,87 @@
+use crate::boid::Boid;
+use crate::life_cycles::Egg;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+const CELL_SIZE: f32 = 150.0;
+
+// Buckets every boid by position so neighbour lookups only visit nearby cells
+#[derive(Resource, Default)]
+pub struct SpatialGrid {
+    cells: HashMap<IVec2, Vec<(Entity, Vec2)>>,
+}
+
+impl SpatialGrid {
+    fn cell_coordinates(position: Vec2) -> IVec2 {
+        (position / CELL_SIZE).floor().as_ivec2()
+    }
+
+    pub fn insert(&mut self, entity: Entity, position: Vec2) {
+        self.cells
+            .entry(Self::cell_coordinates(position))
+            .or_default()
+            .push((entity, position));
+    }
+
+    pub fn clear(&mut self) {
+        // Drop cells that stayed empty since the last rebuild, keep the others' allocations
+        self.cells.retain(|_, entries| {
+            let keep = !entries.is_empty();
+            entries.clear();
+            keep
+        });
+    }
+
+    // Calls `f` with (entity, position, distance squared) for every entry within `radius`
+    pub fn for_each_in_radius(
+        &self,
+        position: Vec2,
+        radius: f32,
+        mut f: impl FnMut(Entity, Vec2, f32),
+    ) {
+        let radius_2 = radius * radius;
+        let min_cell = Self::cell_coordinates(position - Vec2::splat(radius));
+        let max_cell = Self::cell_coordinates(position + Vec2::splat(radius));
+
+        for y in min_cell.y..=max_cell.y {
+            for x in min_cell.x..=max_cell.x {
+                let entries = if let Some(entries) = self.cells.get(&IVec2::new(x, y)) {
+                    entries
+                } else {
+                    continue;
+                };
+
+                for (entity, other_position) in entries.iter() {
+                    let distance_2 = (position - *other_position).length_squared();
+                    if distance_2 <= radius_2 {
+                        f(*entity, *other_position, distance_2);
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn system_update_spatial_grid(
+    mut spatial_grid: ResMut<SpatialGrid>,
+    query: Query<(Entity, &Transform), With<Boid>>,
+) {
+    spatial_grid.clear();
+    for (entity, transform) in query.iter() {
+        spatial_grid.insert(entity, transform.translation.xy());
+    }
+}
+
+// Eggs aren't boids, they get a grid of their own so flocking never runs into them
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct EggSpatialGrid(pub SpatialGrid);
+
+pub fn system_update_egg_spatial_grid(
+    mut egg_spatial_grid: ResMut<EggSpatialGrid>,
+    query: Query<(Entity, &Transform), With<Egg>>,
+) {
+    egg_spatial_grid.clear();
+    for (entity, transform) in query.iter() {
+        egg_spatial_grid.insert(entity, transform.translation.xy());
+    }
+}
@@ -1,8 +1,16 @@
 use crate::boid::Boid;
-use crate::food::Food;
-use crate::{game_state, life_cycles, movement, sprite_animation, threat_boid};
+use crate::difficulty::{CurrentDifficulty, DifficultySettings};
+use crate::food::{Food, FoodKind};
+use crate::genetics::Genome;
+use crate::hunger::Hunger;
+use crate::lineage::{Ancestry, FamilyTree};
+use crate::rng::SimulationRng;
+use crate::terrain::PondMap;
+use crate::tuning::BoidTuning;
+use crate::{duck_boid, game_state, life_cycles, movement, sprite_animation, threat_boid};
+use bevy::ecs::system::EntityCommands;
 use bevy::prelude::*;
-use bevy::window::PrimaryWindow;
+use rand::Rng;
 
 #[derive(Resource, Default)]
 pub struct LoadedAssets {
@@ -10,8 +18,9 @@ pub struct LoadedAssets {
 
     pub cursor_empty_sprite: Handle<Image>,
     pub cursor_food_sprite: Handle<Image>,
+    pub cursor_scare_sprite: Handle<Image>,
 
-    egg_sprite: Handle<Image>,
+    pub egg_sprite: Handle<Image>,
 
     pub duckling_sprite: Handle<Image>,
     pub duckling_atlas: Handle<TextureAtlasLayout>,
@@ -50,6 +59,7 @@ pub fn load_assets(
 
     loaded_assets.cursor_empty_sprite = asset_server.load("cursor/frame_0_delay-0.1s.png");
     loaded_assets.cursor_food_sprite = asset_server.load("cursor/frame_3_delay-0.1s.png");
+    loaded_assets.cursor_scare_sprite = asset_server.load("cursor/frame_2_delay-0.1s.png");
 
     loaded_assets.egg_sprite = asset_server.load("egg.png");
 
@@ -83,9 +93,9 @@ pub fn load_assets(
     let heart_layout = TextureAtlasLayout::from_grid(UVec2::splat(64), 4, 1, None, None);
     loaded_assets.heart_atlas_layout = texture_atlas_layouts.add(heart_layout);
 
-    loaded_assets.food_sprites = FOOD_SPRITES
+    loaded_assets.food_sprites = FoodKind::ALL
         .iter()
-        .map(|path| asset_server.load(*path))
+        .map(|kind| asset_server.load(kind.sprite_path()))
         .collect();
 
     loaded_assets.feather_image = asset_server.load("duckfeather2_edited.png");
@@ -97,102 +107,250 @@ pub fn load_assets(
     loaded_assets.button_clicked_sound = asset_server.load("audio/90s-game-ui-2-185095.mp3");
 }
 
-pub fn system_spawn_boids(mut commands: Commands, loaded_assets: Res<LoadedAssets>) {
+// Part of the world around its center that the round starts in
+const STARTING_AREA_FRACTION: f32 = 0.5;
+// Random spots tried before settling for one that can't be entered, it is walked out of then
+const SPAWN_POSITION_TRIES: usize = 50;
+
+// Random position in `area` around the center that `threat_kind` can be on, `None` for ducks
+fn random_passable_position(
+    area: Vec2,
+    pond_map: &PondMap,
+    threat_kind: Option<threat_boid::ThreatKind>,
+    rng: &mut ResMut<SimulationRng>,
+) -> Vec2 {
+    let mut position = Vec2::ZERO;
+    for _ in 0..SPAWN_POSITION_TRIES {
+        position = (Vec2::new(rng.gen::<f32>(), rng.gen::<f32>()) - 0.5) * area;
+        if pond_map.is_passable(position, threat_kind) {
+            break;
+        }
+    }
+    position
+}
+
+pub fn system_spawn_boids(
+    mut commands: Commands,
+    loaded_assets: Res<LoadedAssets>,
+    world_bounds: Res<movement::WorldBounds>,
+    pond_map: Res<PondMap>,
+    mut family_tree: ResMut<FamilyTree>,
+    mut rng: ResMut<SimulationRng>,
+) {
     for _ in 0..10 {
-        let position = Vec2::new(
-            rand::random::<f32>() * 800.0 - 400.0,
-            rand::random::<f32>() * 600.0 - 300.0,
+        let position = random_passable_position(
+            world_bounds.size * STARTING_AREA_FRACTION,
+            &pond_map,
+            None,
+            &mut rng,
+        );
+        let genome = Genome::random(&mut rng);
+        spawn_boid(
+            position,
+            genome,
+            family_tree.add_founder(),
+            &mut commands,
+            &loaded_assets,
+            &mut rng,
         );
-        spawn_boid(position, &mut commands, &loaded_assets);
     }
 }
 
 pub fn system_spawn_threats(
     mut commands: Commands,
     loaded_assets: Res<LoadedAssets>,
-    mut current_threats: ResMut<CurrentThreats>,
+    current_difficulty: Res<CurrentDifficulty>,
+    world_bounds: Res<movement::WorldBounds>,
+    pond_map: Res<PondMap>,
+    mut rng: ResMut<SimulationRng>,
 ) {
     for index in 0..1 {
-        let position = Vec2::new(
-            rand::random::<f32>() * 800.0 - 400.0,
-            rand::random::<f32>() * 600.0 - 300.0,
+        let position = random_passable_position(
+            world_bounds.size * STARTING_AREA_FRACTION,
+            &pond_map,
+            Some(threat_boid::ThreatKind::Cat),
+            &mut rng,
         );
         spawn_threat(
             position,
+            threat_boid::ThreatKind::Cat,
             &mut commands,
             &loaded_assets,
-            &mut current_threats,
+            &current_difficulty.settings,
+            &mut rng,
             index == 0,
         );
     }
 }
 
+// Kept up to date by the hooks on `Threat`, so threats that leave or get removed are counted out
 #[derive(Resource, Default)]
 pub struct CurrentThreats(pub i32);
-pub fn system_continuous_threat_spawning(
-    mut commands: Commands,
-    loaded_assets: Res<LoadedAssets>,
-    mut current_threats: ResMut<CurrentThreats>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    duck_query: Query<Entity, (With<Boid>, Without<threat_boid::Threat>)>,
-) {
-    let window = if let Ok(window) = window_query.get_single() {
-        window
+
+// How far outside the world threats that walk or fly in appear
+const THREAT_SPAWN_DISTANCE: f32 = 500.0;
+
+// Pikes surface somewhere in the water, everything else comes in from outside, from `angle`
+pub fn threat_spawn_position(
+    kind: threat_boid::ThreatKind,
+    angle: f32,
+    world_size: Vec2,
+    pond_map: &PondMap,
+    rng: &mut ResMut<SimulationRng>,
+) -> Vec2 {
+    if kind == threat_boid::ThreatKind::Pike {
+        random_passable_position(world_size - Vec2::splat(200.0), pond_map, Some(kind), rng)
     } else {
-        return;
-    };
-    let window_width = window.width();
-    let buffer = 500.0;
-
-    let duck_count = duck_query.iter().count();
-    let threat_factor = match duck_count {
-        0..=14 => 7,
-        15..=30 => 10,
-        _ => 20,
+        Vec2::from_angle(angle) * (world_size.x * 0.5 + THREAT_SPAWN_DISTANCE)
+    }
+}
+
+pub fn spawn_boid(
+    position: Vec2,
+    genome: Genome,
+    ancestry: Ancestry,
+    commands: &mut Commands,
+    loaded_assets: &Res<LoadedAssets>,
+    rng: &mut ResMut<SimulationRng>,
+) {
+    let hatch_time = rng.gen::<f32>()
+        * (life_cycles::EGG_HATCH_TIME_MAX - life_cycles::EGG_HATCH_TIME_MIN)
+        + life_cycles::EGG_HATCH_TIME_MIN;
+    spawn_egg(
+        position,
+        hatch_time,
+        genome,
+        ancestry,
+        commands,
+        loaded_assets,
+    );
+}
+
+pub fn spawn_egg(
+    position: Vec2,
+    hatch_time: f32,
+    genome: Genome,
+    ancestry: Ancestry,
+    commands: &mut Commands,
+    loaded_assets: &LoadedAssets,
+) -> Entity {
+    spawn_life_stage_sprite(
+        position,
+        life_cycles::LifeStage::Egg,
+        hatch_time,
+        genome,
+        ancestry,
+        commands,
+        loaded_assets,
+    )
+    .insert(life_cycles::Egg)
+    .id()
+}
+
+// Spawns a duck that is already in the given stage, e.g. when loading a saved game
+pub fn spawn_duck(
+    position: Vec2,
+    stage: life_cycles::LifeStage,
+    life_cycle_time: f32,
+    hunger: f32,
+    genome: Genome,
+    ancestry: Ancestry,
+    velocity: Vec2,
+    commands: &mut Commands,
+    loaded_assets: &LoadedAssets,
+    boid_tuning: &BoidTuning,
+) -> Entity {
+    let boid_profile = match stage {
+        life_cycles::LifeStage::Egg => {
+            return spawn_egg(
+                position,
+                life_cycle_time,
+                genome,
+                ancestry,
+                commands,
+                loaded_assets,
+            );
+        }
+        life_cycles::LifeStage::Duckling => boid_tuning.duckling_profile.clone(),
+        life_cycles::LifeStage::Juvenile => boid_tuning.juvenile_profile.clone(),
+        life_cycles::LifeStage::Adult | life_cycles::LifeStage::Elder => {
+            boid_tuning.adult_profile.clone()
+        }
     };
 
-    if current_threats.0 * threat_factor < duck_count as i32 {
-        let random_position_on_circle =
-            Vec2::new(rand::random::<f32>().cos(), rand::random::<f32>().sin())
-                * (window_width * 0.5 + buffer);
-        spawn_threat(
-            random_position_on_circle,
-            &mut commands,
-            &loaded_assets,
-            &mut current_threats,
-            false,
-        );
+    let mut entity_commands = spawn_life_stage_sprite(
+        position,
+        stage,
+        life_cycle_time,
+        genome,
+        ancestry,
+        commands,
+        loaded_assets,
+    );
+    match stage {
+        life_cycles::LifeStage::Duckling => {
+            entity_commands.insert(life_cycles::Duckling);
+        }
+        life_cycles::LifeStage::Juvenile => {
+            entity_commands.insert(life_cycles::Juvenile);
+        }
+        life_cycles::LifeStage::Elder => {
+            entity_commands
+                .insert(life_cycles::Adult)
+                .insert(life_cycles::Elder)
+                .insert(duck_boid::CloseAdults::default());
+        }
+        _ => {
+            entity_commands
+                .insert(life_cycles::Adult)
+                .insert(duck_boid::CloseAdults::default());
+        }
     }
+
+    entity_commands
+        .insert(Boid)
+        .insert(boid_profile)
+        .insert(Hunger(hunger))
+        .insert(movement::Velocity(velocity))
+        .insert(movement::VelocityLimits::default())
+        .id()
 }
 
-pub fn spawn_boid(position: Vec2, commands: &mut Commands, loaded_assets: &Res<LoadedAssets>) {
-    commands
-        .spawn(SpriteBundle {
-            texture: loaded_assets.egg_sprite.clone(),
-            transform: Transform {
-                translation: position.extend(-1.0),
-                scale: Vec3::splat(0.5),
-                ..Default::default()
-            },
+fn spawn_life_stage_sprite<'a>(
+    position: Vec2,
+    stage: life_cycles::LifeStage,
+    life_cycle_time: f32,
+    genome: Genome,
+    ancestry: Ancestry,
+    commands: &'a mut Commands,
+    loaded_assets: &LoadedAssets,
+) -> EntityCommands<'a> {
+    let stage_sprite = life_cycles::stage_sprite(stage, loaded_assets);
+    let mut entity_commands = commands.spawn(SpriteBundle {
+        sprite: Sprite {
+            color: genome.tinted(stage_sprite.color),
             ..Default::default()
+        },
+        texture: stage_sprite.image,
+        transform: Transform {
+            translation: position.extend(stage_sprite.z_value),
+            scale: Vec3::splat(stage_sprite.scale * genome.size),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    entity_commands
+        .insert(life_cycles::LifeCycleTime(life_cycle_time))
+        .insert(genome)
+        .insert(ancestry)
+        .insert(TextureAtlas {
+            layout: stage_sprite.atlas,
+            index: stage_sprite.animation_indices.first,
         })
-        .insert(life_cycles::Egg)
-        .insert(life_cycles::LifeCycleTime(
-            rand::random::<f32>()
-                * (life_cycles::EGG_HATCH_TIME_MAX - life_cycles::EGG_HATCH_TIME_MIN)
-                + life_cycles::EGG_HATCH_TIME_MIN,
-        ))
-        .insert(sprite_animation::AnimationIndices {
-            first: 0,
-            last: 0,
-            paused: false,
-        })
-        .insert(sprite_animation::AnimationTimer(Timer::from_seconds(
-            0.25,
-            TimerMode::Once,
-        )))
-        .insert(TextureAtlas::default())
+        .insert(stage_sprite.animation_indices)
+        .insert(stage_sprite.animation_timer)
         .insert(game_state::RemoveOnRestart);
+    entity_commands
 }
 
 const CAT_VARIATION_ASSETS: [&str; 4] = [
@@ -204,87 +362,147 @@ const CAT_VARIATION_ASSETS: [&str; 4] = [
 
 pub fn spawn_threat(
     position: Vec2,
+    kind: threat_boid::ThreatKind,
     commands: &mut Commands,
     loaded_assets: &Res<LoadedAssets>,
-    current_threats: &mut ResMut<CurrentThreats>,
+    difficulty_settings: &DifficultySettings,
+    rng: &mut ResMut<SimulationRng>,
     tabby: bool,
 ) {
-    current_threats.0 += 1;
+    let random_animation_start_index = rng.gen::<usize>() % 4;
+    let random_animation_timer: f32 = rng.gen::<f32>() * 0.5 + 1.0;
+    let random_index = rng.gen::<usize>() % CAT_VARIATION_ASSETS.len();
+    let cat_variation = match kind {
+        threat_boid::ThreatKind::Cat if tabby => None,
+        threat_boid::ThreatKind::Cat => Some(random_index),
+        threat_boid::ThreatKind::Hawk => Some(0),
+        threat_boid::ThreatKind::Pike => Some(1),
+        threat_boid::ThreatKind::Fox => Some(3),
+    };
+
+    spawn_threat_with(
+        position,
+        threat_boid::Threat {
+            kind,
+            home: position,
+            cat_variation,
+            ..Default::default()
+        },
+        Vec2::ZERO,
+        random_animation_start_index,
+        random_animation_timer,
+        commands,
+        loaded_assets,
+        difficulty_settings,
+    );
+}
+
+pub fn spawn_threat_with(
+    position: Vec2,
+    threat: threat_boid::Threat,
+    velocity: Vec2,
+    animation_start_index: usize,
+    animation_frame_duration: f32,
+    commands: &mut Commands,
+    loaded_assets: &LoadedAssets,
+    difficulty_settings: &DifficultySettings,
+) -> Entity {
     let walking_animation_indices = sprite_animation::AnimationIndices {
         first: 0,
         last: 3,
         paused: false,
     };
-    let random_animation_start_index = rand::random::<usize>() % 4;
-    let random_animation_timer: f32 = rand::random::<f32>() * 0.5 + 1.0;
-    let random_index = rand::random::<usize>() % CAT_VARIATION_ASSETS.len();
 
-    let texture = if tabby {
-        loaded_assets.tabby_sprite.clone()
+    let texture = if let Some(cat_variation) = threat.cat_variation {
+        // The sprite lists stay empty when running headless without an asset server
+        loaded_assets
+            .threat_sprites
+            .get(cat_variation)
+            .cloned()
+            .unwrap_or_default()
     } else {
-        loaded_assets.threat_sprites[random_index].clone()
+        loaded_assets.tabby_sprite.clone()
     };
+    let kind = threat.kind;
     commands
         .spawn(SpriteBundle {
             sprite: Sprite {
                 flip_x: true,
+                color: kind.tint(),
                 ..Default::default()
             },
             texture,
             transform: Transform {
                 translation: position.extend(5.0),
-                scale: Vec3::splat(3.0),
+                scale: Vec3::splat(kind.scale()),
                 ..Default::default()
             },
             ..Default::default()
         })
-        .insert(threat_boid::Threat::default())
-        .insert(movement::Velocity::default())
+        .insert(threat)
+        .insert(movement::Velocity(velocity))
         .insert(movement::VelocityLimits {
             min: 0.0,
-            max: 200.0,
+            max: kind.max_speed() * difficulty_settings.threat_speed_factor,
         })
         .insert((
             TextureAtlas {
                 layout: loaded_assets.threat_walking_atlas.clone(),
-                index: random_animation_start_index,
+                index: animation_start_index,
             },
             walking_animation_indices,
             sprite_animation::AnimationTimer(Timer::from_seconds(
-                random_animation_timer,
+                animation_frame_duration,
                 TimerMode::Repeating,
             )),
         ))
-        .insert(game_state::RemoveOnRestart);
+        .insert(game_state::RemoveOnRestart)
+        .id()
 }
 
-const FOOD_SPRITES: [&str; 9] = [
-    "foods/bread.png",
-    "foods/bread.png",
-    "foods/bread.png",
-    "foods/bread.png",
-    "foods/pretzel.png",
-    "foods/pretzel.png",
-    "foods/cake.png",
-    "foods/croissant.png",
-    "foods/croissant.png",
-];
-
-pub fn spawn_food(position: Vec2, commands: &mut Commands, loaded_assets: &Res<LoadedAssets>) {
-    let random_index = rand::random::<usize>() % FOOD_SPRITES.len();
+pub fn spawn_food(
+    position: Vec2,
+    kind: FoodKind,
+    commands: &mut Commands,
+    loaded_assets: &Res<LoadedAssets>,
+    rng: &mut ResMut<SimulationRng>,
+) {
+    let random_rotation = rng.gen::<f32>() * std::f32::consts::PI;
+    spawn_food_with(
+        position,
+        kind,
+        0.0,
+        random_rotation,
+        commands,
+        loaded_assets,
+    );
+}
 
+pub fn spawn_food_with(
+    position: Vec2,
+    kind: FoodKind,
+    age: f32,
+    rotation: f32,
+    commands: &mut Commands,
+    loaded_assets: &LoadedAssets,
+) -> Entity {
     commands
         .spawn(SpriteBundle {
-            texture: loaded_assets.food_sprites[random_index].clone(),
+            texture: loaded_assets
+                .food_sprites
+                .get(kind as usize)
+                .cloned()
+                .unwrap_or_default(),
             transform: Transform {
                 translation: position.extend(-2.0),
                 scale: Vec3::splat(1.15),
-                rotation: Quat::from_rotation_z(rand::random::<f32>() * std::f32::consts::PI),
+                rotation: Quat::from_rotation_z(rotation),
             },
             ..Default::default()
         })
-        .insert(Food)
-        .insert(game_state::RemoveOnRestart);
+        .insert(Food { kind, age })
+        .insert(game_state::RemoveOnRestart)
+        .id()
 }
 
 pub fn spawn_relationship_sprite(
@@ -0,0 +1,407 @@
+use crate::movement::Velocity;
+use crate::simulation::LoadedConfigs;
+use crate::threat_boid::{Threat, ThreatKind};
+use crate::tuning::BoidTuning;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoadFailedEvent, AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub const POND_MAP_PATH: &str = "config/pond.map.ron";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub enum TerrainKind {
+    // Ducks swim fastest here, cats and foxes can't go in
+    DeepWater,
+    #[default]
+    ShallowWater,
+    // Slows down everything that doesn't fly
+    Reeds,
+    // Ducks waddle, pikes can't go on it
+    Land,
+    // Nothing gets through
+    Rock,
+}
+
+impl TerrainKind {
+    // The characters used in `PondMap::rows`
+    pub fn from_char(character: char) -> Option<TerrainKind> {
+        match character {
+            '~' => Some(TerrainKind::DeepWater),
+            '-' => Some(TerrainKind::ShallowWater),
+            '|' => Some(TerrainKind::Reeds),
+            '.' => Some(TerrainKind::Land),
+            '#' => Some(TerrainKind::Rock),
+            _ => None,
+        }
+    }
+
+    // `None` for ducks
+    pub fn is_passable(self, threat_kind: Option<ThreatKind>) -> bool {
+        !matches!(
+            (self, threat_kind),
+            (TerrainKind::Rock, _)
+                | (
+                    TerrainKind::DeepWater,
+                    Some(ThreatKind::Cat | ThreatKind::Fox)
+                )
+                | (TerrainKind::Land, Some(ThreatKind::Pike))
+        )
+    }
+
+    // Reeds grow in the shallows, so they count too
+    pub fn is_water(self) -> bool {
+        matches!(
+            self,
+            TerrainKind::DeepWater | TerrainKind::ShallowWater | TerrainKind::Reeds
+        )
+    }
+
+    // Factor on the top speed, `None` for ducks
+    pub fn speed_factor(self, threat_kind: Option<ThreatKind>) -> f32 {
+        match (self, threat_kind) {
+            (_, Some(ThreatKind::Hawk)) => 1.0,
+            (TerrainKind::DeepWater, None) => 1.25,
+            (TerrainKind::Land, None) => 0.6,
+            (TerrainKind::ShallowWater, Some(ThreatKind::Cat | ThreatKind::Fox)) => 0.6,
+            (TerrainKind::Reeds, _) => 0.7,
+            _ => 1.0,
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            TerrainKind::DeepWater => Color::srgb(0.16, 0.36, 0.55),
+            TerrainKind::ShallowWater => Color::srgb(0.3, 0.55, 0.7),
+            TerrainKind::Reeds => Color::srgb(0.4, 0.55, 0.3),
+            TerrainKind::Land => Color::srgb(0.45, 0.62, 0.32),
+            TerrainKind::Rock => Color::srgb(0.45, 0.45, 0.45),
+        }
+    }
+}
+
+// Tile map of the pond, loaded from `POND_MAP_PATH`.
+// Centered on the origin like `movement::WorldBounds`, everything outside the rows is `default_kind`.
+#[derive(Asset, TypePath, Resource, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct PondMap {
+    pub tile_size: f32,
+    pub default_kind: TerrainKind,
+    // One character per tile, top row first, see `TerrainKind::from_char`
+    pub rows: Vec<String>,
+}
+
+// An open pond without any obstacles
+impl Default for PondMap {
+    fn default() -> Self {
+        Self {
+            tile_size: 60.0,
+            default_kind: TerrainKind::ShallowWater,
+            rows: Vec::new(),
+        }
+    }
+}
+
+// How far a boid stuck in an obstacle looks for a way out
+const ESCAPE_SEARCH_TILES: i32 = 4;
+
+impl PondMap {
+    pub fn size_in_tiles(&self) -> UVec2 {
+        let width = self.rows.iter().map(String::len).max().unwrap_or(0);
+        UVec2::new(width as u32, self.rows.len() as u32)
+    }
+
+    fn top_left(&self) -> Vec2 {
+        let size = self.size_in_tiles().as_vec2() * self.tile_size;
+        Vec2::new(-size.x, size.y) * 0.5
+    }
+
+    pub fn tile_at(&self, position: Vec2) -> IVec2 {
+        let offset = (position - self.top_left()) / self.tile_size;
+        IVec2::new(offset.x.floor() as i32, (-offset.y).floor() as i32)
+    }
+
+    pub fn tile_center(&self, tile: IVec2) -> Vec2 {
+        self.top_left() + Vec2::new(tile.x as f32 + 0.5, -(tile.y as f32 + 0.5)) * self.tile_size
+    }
+
+    pub fn kind_of_tile(&self, tile: IVec2) -> TerrainKind {
+        if tile.x < 0 || tile.y < 0 {
+            return self.default_kind;
+        }
+        self.rows
+            .get(tile.y as usize)
+            .and_then(|row| row.as_bytes().get(tile.x as usize))
+            .and_then(|character| TerrainKind::from_char(*character as char))
+            .unwrap_or(self.default_kind)
+    }
+
+    pub fn kind_at(&self, position: Vec2) -> TerrainKind {
+        self.kind_of_tile(self.tile_at(position))
+    }
+
+    pub fn is_passable(&self, position: Vec2, threat_kind: Option<ThreatKind>) -> bool {
+        self.kind_at(position).is_passable(threat_kind)
+    }
+
+    // The center of the closest tile around `position` that can be entered, if there is one nearby
+    fn closest_passable_tile_center(
+        &self,
+        position: Vec2,
+        threat_kind: Option<ThreatKind>,
+    ) -> Option<Vec2> {
+        let center_tile = self.tile_at(position);
+        let mut closest: Option<(Vec2, f32)> = None;
+        for y in -ESCAPE_SEARCH_TILES..=ESCAPE_SEARCH_TILES {
+            for x in -ESCAPE_SEARCH_TILES..=ESCAPE_SEARCH_TILES {
+                let tile = center_tile + IVec2::new(x, y);
+                if !self.kind_of_tile(tile).is_passable(threat_kind) {
+                    continue;
+                }
+                let tile_center = self.tile_center(tile);
+                let distance = tile_center.distance_squared(position);
+                if closest.is_none_or(|(_, closest_distance)| distance < closest_distance) {
+                    closest = Some((tile_center, distance));
+                }
+            }
+        }
+        closest.map(|(tile_center, _)| tile_center)
+    }
+}
+
+#[derive(Default)]
+pub struct PondMapLoader;
+
+impl AssetLoader for PondMapLoader {
+    type Asset = PondMap;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes::<PondMap>(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["map.ron"]
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct PondMapHandle(pub Handle<PondMap>);
+
+pub fn system_load_pond_map(
+    asset_server: Res<AssetServer>,
+    mut pond_map_handle: ResMut<PondMapHandle>,
+) {
+    pond_map_handle.0 = asset_server.load(POND_MAP_PATH);
+}
+
+// Copies the asset into the `PondMap` resource whenever it is (re)loaded
+pub fn system_apply_pond_map(
+    mut asset_events: EventReader<AssetEvent<PondMap>>,
+    mut load_failed_events: EventReader<AssetLoadFailedEvent<PondMap>>,
+    pond_map_assets: Res<Assets<PondMap>>,
+    pond_map_handle: Res<PondMapHandle>,
+    mut pond_map: ResMut<PondMap>,
+    mut loaded_configs: ResMut<LoadedConfigs>,
+) {
+    for event in load_failed_events.read() {
+        warn!(
+            "Could not load {}, using an open pond: {}",
+            event.path, event.error
+        );
+        loaded_configs.pond_map = true;
+    }
+
+    for event in asset_events.read() {
+        match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => {
+                if *id != pond_map_handle.0.id() {
+                    continue;
+                }
+                if let Some(loaded_pond_map) = pond_map_assets.get(*id) {
+                    *pond_map = loaded_pond_map.clone();
+                    loaded_configs.pond_map = true;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct TerrainTile;
+
+// Redraws the whole map whenever it changes
+pub fn system_spawn_terrain_tiles(
+    mut commands: Commands,
+    pond_map: Res<PondMap>,
+    tile_query: Query<Entity, With<TerrainTile>>,
+) {
+    if !pond_map.is_changed() {
+        return;
+    }
+
+    for entity in tile_query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let size_in_tiles = pond_map.size_in_tiles().as_ivec2();
+    for y in 0..size_in_tiles.y {
+        for x in 0..size_in_tiles.x {
+            let tile = IVec2::new(x, y);
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: pond_map.kind_of_tile(tile).color(),
+                        custom_size: Some(Vec2::splat(pond_map.tile_size)),
+                        ..Default::default()
+                    },
+                    transform: Transform::from_translation(
+                        pond_map.tile_center(tile).extend(-10.0),
+                    ),
+                    ..Default::default()
+                },
+                TerrainTile,
+            ));
+        }
+    }
+}
+
+// How many tiles around a boid are checked for obstacles
+const OBSTACLE_SEARCH_TILES: i32 = 1;
+// Pushes boids away from the tiles they can't enter, stronger the closer they get
+pub fn system_avoid_obstacles(
+    time: Res<Time>,
+    boid_tuning: Res<BoidTuning>,
+    pond_map: Res<PondMap>,
+    mut query: Query<(&mut Velocity, &Transform, Option<&Threat>)>,
+) {
+    let avoid_radius = pond_map.tile_size * (OBSTACLE_SEARCH_TILES as f32 + 0.5);
+
+    for (mut velocity, transform, threat) in query.iter_mut() {
+        let threat_kind = threat.map(|threat| threat.kind);
+        let position = transform.translation.xy();
+        let center_tile = pond_map.tile_at(position);
+        // Stuck inside an obstacle, like after the map changed under it, it heads for the closest
+        // way out. Movement doesn't block it until it is out.
+        if !pond_map.kind_of_tile(center_tile).is_passable(threat_kind) {
+            if let Some(way_out) = pond_map.closest_passable_tile_center(position, threat_kind) {
+                velocity.0 += (way_out - position).normalize_or_zero()
+                    * boid_tuning.avoid_obstacles_factor
+                    * time.delta_seconds();
+            }
+            continue;
+        }
+
+        let mut push = Vec2::ZERO;
+        for y in -OBSTACLE_SEARCH_TILES..=OBSTACLE_SEARCH_TILES {
+            for x in -OBSTACLE_SEARCH_TILES..=OBSTACLE_SEARCH_TILES {
+                let tile = center_tile + IVec2::new(x, y);
+                if pond_map.kind_of_tile(tile).is_passable(threat_kind) {
+                    continue;
+                }
+
+                // Away from the closest point of the tile
+                let tile_center = pond_map.tile_center(tile);
+                let half_size = Vec2::splat(pond_map.tile_size * 0.5);
+                let closest_point =
+                    position.clamp(tile_center - half_size, tile_center + half_size);
+                let away = position - closest_point;
+                let distance = away.length();
+                if distance < avoid_radius && distance > 0.0 {
+                    push += away / distance * (1.0 - distance / avoid_radius);
+                }
+            }
+        }
+
+        velocity.0 += push * boid_tuning.avoid_obstacles_factor * time.delta_seconds();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_map() -> PondMap {
+        PondMap {
+            tile_size: 10.0,
+            default_kind: TerrainKind::ShallowWater,
+            rows: vec!["~~#".to_string(), "..|".to_string()],
+        }
+    }
+
+    #[test]
+    fn tile_center_round_trips_through_tile_at() {
+        let pond_map = test_map();
+        for y in -1..3 {
+            for x in -1..4 {
+                let tile = IVec2::new(x, y);
+                assert_eq!(pond_map.tile_at(pond_map.tile_center(tile)), tile);
+            }
+        }
+    }
+
+    #[test]
+    fn rows_go_top_down() {
+        let pond_map = test_map();
+        // 3 by 2 tiles centered on the origin, the first row is above it
+        assert_eq!(
+            pond_map.tile_center(IVec2::new(0, 0)),
+            Vec2::new(-10.0, 5.0)
+        );
+        assert_eq!(
+            pond_map.tile_center(IVec2::new(2, 1)),
+            Vec2::new(10.0, -5.0)
+        );
+        assert_eq!(pond_map.tile_at(Vec2::new(-12.0, 8.0)), IVec2::new(0, 0));
+        assert_eq!(pond_map.tile_at(Vec2::new(12.0, -8.0)), IVec2::new(2, 1));
+
+        assert_eq!(
+            pond_map.kind_at(Vec2::new(-10.0, 5.0)),
+            TerrainKind::DeepWater
+        );
+        assert_eq!(pond_map.kind_at(Vec2::new(10.0, 5.0)), TerrainKind::Rock);
+        assert_eq!(pond_map.kind_at(Vec2::new(-10.0, -5.0)), TerrainKind::Land);
+        assert_eq!(pond_map.kind_at(Vec2::new(10.0, -5.0)), TerrainKind::Reeds);
+    }
+
+    #[test]
+    fn outside_the_rows_is_the_default_kind() {
+        let pond_map = test_map();
+        assert_eq!(
+            pond_map.kind_at(Vec2::new(0.0, 100.0)),
+            TerrainKind::ShallowWater
+        );
+        assert_eq!(
+            pond_map.kind_at(Vec2::new(-100.0, 0.0)),
+            TerrainKind::ShallowWater
+        );
+        assert_eq!(
+            pond_map.kind_of_tile(IVec2::new(3, 0)),
+            TerrainKind::ShallowWater
+        );
+    }
+
+    #[test]
+    fn stuck_boids_find_the_closest_passable_tile() {
+        let pond_map = test_map();
+        // A duck on the rock gets out through the reeds below it
+        assert_eq!(
+            pond_map.closest_passable_tile_center(Vec2::new(10.0, 1.0), None),
+            Some(Vec2::new(10.0, -5.0))
+        );
+        // A cat in the deep water gets out onto the land below it
+        assert_eq!(
+            pond_map.closest_passable_tile_center(Vec2::new(-10.0, 1.0), Some(ThreatKind::Cat)),
+            Some(Vec2::new(-10.0, -5.0))
+        );
+    }
+}
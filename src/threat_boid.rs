@@ -1,82 +1,511 @@
-use crate::spawning::LoadedAssets;
-use crate::{audio, boid, movement, sprite_animation, vfx};
+use crate::rng::SimulationRng;
+use crate::spatial_grid::{EggSpatialGrid, SpatialGrid};
+use crate::spawning::{CurrentThreats, LoadedAssets};
+use crate::terrain::PondMap;
+use crate::tuning::BoidTuning;
+use crate::{boid, life_cycles, movement, sprite_animation};
+use bevy::ecs::component::{ComponentHooks, StorageType};
 use bevy::prelude::*;
-use bevy::window::PrimaryWindow;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-#[derive(Component, Default)]
+#[derive(Event)]
+pub struct DuckEaten {
+    pub position: Vec2,
+}
+
+#[derive(Event)]
+pub struct EggEaten {
+    pub position: Vec2,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub enum ThreatKind {
+    #[default]
+    Cat,
+    // Glides in from off-screen and dives at ducks from far away
+    Hawk,
+    // Stays in the water and only hunts ducks that are in it too
+    Pike,
+    // Goes for eggs before anything else
+    Fox,
+}
+
+// How much a threat wants each life stage, 0.0 for the ones it leaves alone
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PreyPreferences {
+    pub egg: f32,
+    pub duckling: f32,
+    pub juvenile: f32,
+    pub adult: f32,
+}
+
+impl PreyPreferences {
+    pub fn weight(&self, stage: life_cycles::LifeStage) -> f32 {
+        match stage {
+            life_cycles::LifeStage::Egg => self.egg,
+            life_cycles::LifeStage::Duckling => self.duckling,
+            life_cycles::LifeStage::Juvenile => self.juvenile,
+            life_cycles::LifeStage::Adult | life_cycles::LifeStage::Elder => self.adult,
+        }
+    }
+}
+
+// How a kind of threat moves and what it hunts
+pub struct ThreatProfile {
+    pub visibility_radius: f32,
+    pub pounce_radius: f32,
+    pub wander_speed: f32,
+    pub stalk_speed: f32,
+    pub pounce_speed: f32,
+    pub pounce_duration: f32,
+    pub rest_duration: f32,
+    // Climbs away after a dive instead of resting
+    pub flees_after_pounce: bool,
+    // Leaves the pond once it ate this many
+    pub meals_before_leaving: u32,
+    // Leaves prey alone that isn't on a water tile of the `PondMap`
+    pub hunts_only_in_water: bool,
+}
+
+impl ThreatKind {
+    pub fn profile(self) -> ThreatProfile {
+        match self {
+            ThreatKind::Cat => ThreatProfile {
+                visibility_radius: 145.0,
+                pounce_radius: 70.0,
+                wander_speed: 40.0,
+                stalk_speed: 70.0,
+                pounce_speed: 200.0,
+                pounce_duration: 0.6,
+                rest_duration: 2.0,
+                flees_after_pounce: false,
+                meals_before_leaving: 3,
+                hunts_only_in_water: false,
+            },
+            ThreatKind::Hawk => ThreatProfile {
+                visibility_radius: 300.0,
+                pounce_radius: 300.0,
+                wander_speed: 120.0,
+                stalk_speed: 120.0,
+                pounce_speed: 350.0,
+                pounce_duration: 1.5,
+                rest_duration: 0.0,
+                flees_after_pounce: true,
+                meals_before_leaving: 2,
+                hunts_only_in_water: false,
+            },
+            ThreatKind::Pike => ThreatProfile {
+                visibility_radius: 110.0,
+                pounce_radius: 60.0,
+                wander_speed: 25.0,
+                stalk_speed: 45.0,
+                pounce_speed: 220.0,
+                pounce_duration: 0.4,
+                rest_duration: 3.0,
+                flees_after_pounce: false,
+                meals_before_leaving: 4,
+                hunts_only_in_water: true,
+            },
+            ThreatKind::Fox => ThreatProfile {
+                visibility_radius: 180.0,
+                pounce_radius: 60.0,
+                wander_speed: 50.0,
+                stalk_speed: 60.0,
+                pounce_speed: 180.0,
+                pounce_duration: 0.6,
+                rest_duration: 2.0,
+                flees_after_pounce: false,
+                meals_before_leaving: 3,
+                hunts_only_in_water: false,
+            },
+        }
+    }
+
+    // There are only cat sprites, the other kinds are told apart by their tint and size
+    pub fn tint(self) -> Color {
+        match self {
+            ThreatKind::Cat => Color::WHITE,
+            ThreatKind::Hawk => Color::srgb(0.75, 0.75, 0.85),
+            ThreatKind::Pike => Color::srgba(0.5, 0.8, 0.6, 0.75),
+            ThreatKind::Fox => Color::srgb(1.0, 0.6, 0.3),
+        }
+    }
+
+    pub fn scale(self) -> f32 {
+        match self {
+            ThreatKind::Cat | ThreatKind::Fox => 3.0,
+            ThreatKind::Hawk => 3.5,
+            ThreatKind::Pike => 2.5,
+        }
+    }
+
+    pub fn max_speed(self) -> f32 {
+        self.profile().pounce_speed.max(FLEE_SPEED)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub enum ThreatState {
+    // Strolls around until it spots prey
+    #[default]
+    Wander,
+    // Sneaks up on its prey
+    Stalk,
+    // Short dash at the prey once it got close enough
+    Pounce,
+    // Runs away from `Threat::flee_from`
+    Flee,
+    // Sits still after eating or a missed pounce
+    Rest,
+    // Heads off the screen for good, after eating enough or being scared too often
+    Leave,
+}
+
+impl ThreatState {
+    pub fn is_running(self) -> bool {
+        matches!(
+            self,
+            ThreatState::Pounce | ThreatState::Flee | ThreatState::Leave
+        )
+    }
+}
+
+#[derive(Default)]
 pub struct Threat {
-    pub eating_cooldown: f32,
+    pub kind: ThreatKind,
+    // Where it was spawned
+    pub home: Vec2,
+    pub state: ThreatState,
+    // Seconds left in a timed state, or until the next turn while wandering
+    pub state_timer: f32,
+    pub wander_direction: Vec2,
+    pub flee_from: Vec2,
+    pub meals: u32,
+    pub scares: u32,
+    // Whether the running animation is playing
     pub running: bool,
+    // Which of the cat sprites it uses, `None` for the tabby
+    pub cat_variation: Option<usize>,
+}
+
+impl Threat {
+    pub fn set_state(&mut self, state: ThreatState, duration: f32) {
+        self.state = state;
+        self.state_timer = duration;
+    }
+
+    // Makes the threat run away from `position`
+    pub fn flee(&mut self, position: Vec2) {
+        self.flee_from = position;
+        self.set_state(ThreatState::Flee, FLEE_DURATION);
+    }
+
+    // Like `flee`, but a threat that keeps getting scared leaves
+    pub fn scare(&mut self, position: Vec2) {
+        self.scares += 1;
+        if self.scares >= SCARES_BEFORE_LEAVING {
+            self.set_state(ThreatState::Leave, 0.0);
+        } else {
+            self.flee(position);
+        }
+    }
+}
+
+// Keeps `CurrentThreats` in sync with the threats that are actually around
+impl Component for Threat {
+    const STORAGE_TYPE: StorageType = StorageType::Table;
+
+    fn register_component_hooks(hooks: &mut ComponentHooks) {
+        hooks
+            .on_add(|mut world, _, _| {
+                if let Some(mut current_threats) = world.get_resource_mut::<CurrentThreats>() {
+                    current_threats.0 += 1;
+                }
+            })
+            .on_remove(|mut world, _, _| {
+                if let Some(mut current_threats) = world.get_resource_mut::<CurrentThreats>() {
+                    current_threats.0 -= 1;
+                }
+            });
+    }
 }
 
-const THREAT_VISIBILITY_RADIUS_2: f32 = 145.0 * 145.0;
-const TOWARDS_CLOSEST_DUCK_FACTOR: f32 = 300.0;
-const DECELERATION_FACTOR: f32 = 150.0;
 const THREAT_EATING_RADIUS_2: f32 = 30.0 * 30.0;
-const THREAT_EATING_COOLDOWN_DURATION: f32 = 2.0;
-pub fn system_boid_towards_closest_duck(
+
+const WANDER_ACCELERATION: f32 = 60.0;
+const WANDER_TURN_INTERVAL: f32 = 3.0;
+const FLEE_SPEED: f32 = 200.0;
+const FLEE_DURATION: f32 = 3.0;
+const DECELERATION_FACTOR: f32 = 150.0;
+const MISSED_POUNCE_REST_DURATION: f32 = 1.0;
+const SCARES_BEFORE_LEAVING: u32 = 2;
+// How far past the world edge a leaving threat gets despawned
+const LEAVE_MARGIN: f32 = 100.0;
+
+const MOBBED_RADIUS: f32 = 60.0;
+const MOB_SIZE: usize = 3;
+// Enough adults crowding a threat scare it off
+pub fn system_threats_flee_from_mobs(
+    spatial_grid: Res<SpatialGrid>,
+    adult_query: Query<(), With<life_cycles::Adult>>,
+    mut threat_query: Query<(&Transform, &mut Threat)>,
+) {
+    for (threat_transform, mut threat) in threat_query.iter_mut() {
+        if matches!(threat.state, ThreatState::Flee | ThreatState::Leave) {
+            continue;
+        }
+
+        let mut mob_size = 0;
+        let mut mob_position_sum = Vec2::ZERO;
+        spatial_grid.for_each_in_radius(
+            threat_transform.translation.xy(),
+            MOBBED_RADIUS,
+            |entity, position, _| {
+                if adult_query.contains(entity) {
+                    mob_size += 1;
+                    mob_position_sum += position;
+                }
+            },
+        );
+
+        if mob_size >= MOB_SIZE {
+            threat.scare(mob_position_sum / mob_size as f32);
+        }
+    }
+}
+
+// Changes the velocity towards `target_velocity` by at most `acceleration` per second
+fn steer_towards(
+    velocity: &mut Vec2,
+    target_velocity: Vec2,
+    acceleration: f32,
+    delta_seconds: f32,
+) {
+    let difference = target_velocity - *velocity;
+    *velocity += difference.clamp_length_max(acceleration * delta_seconds);
+}
+
+pub fn system_threat_hunting(
     time: Res<Time>,
+    boid_tuning: Res<BoidTuning>,
     mut commands: Commands,
-    duck_query: Query<(Entity, &Transform), (With<boid::Boid>, Without<Threat>)>,
-    mut threat_query: Query<(&Transform, &mut movement::Velocity, &mut Threat)>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    loaded_assets: Res<LoadedAssets>,
-    active_audio_sources: Res<audio::ActiveAudioSources>,
+    spatial_grid: Res<SpatialGrid>,
+    egg_spatial_grid: Res<EggSpatialGrid>,
+    duck_query: Query<
+        (Has<life_cycles::Duckling>, Has<life_cycles::Juvenile>),
+        (With<boid::Boid>, Without<Threat>),
+    >,
+    mut threat_query: Query<(Entity, &Transform, &mut movement::Velocity, &mut Threat)>,
+    world_bounds: Res<movement::WorldBounds>,
+    pond_map: Res<PondMap>,
+    mut rng: ResMut<SimulationRng>,
+    mut duck_eaten_events: EventWriter<DuckEaten>,
+    mut egg_eaten_events: EventWriter<EggEaten>,
 ) {
-    for (threat_transform, mut threat_velocity, mut threat) in threat_query.iter_mut() {
-        threat.eating_cooldown -= time.delta_seconds();
-
-        let mut closest_duck: Option<(Vec2, f32, Entity)> = None;
-        for (duck_entity, duck_transform) in duck_query.iter() {
-            let distance_2 = (duck_transform.translation.xy() - threat_transform.translation.xy())
-                .length_squared();
-            if distance_2 < THREAT_VISIBILITY_RADIUS_2
-                && (closest_duck.is_none() || distance_2 < closest_duck.unwrap().1)
-            {
-                closest_duck = Some((duck_transform.translation.xy(), distance_2, duck_entity));
-            }
-        }
+    let delta_seconds = time.delta_seconds();
+    for (threat_entity, threat_transform, mut threat_velocity, mut threat) in
+        threat_query.iter_mut()
+    {
+        threat.state_timer -= delta_seconds;
+        let threat_position = threat_transform.translation.xy();
+        let profile = threat.kind.profile();
+        let in_hunting_range =
+            |position: Vec2| !profile.hunts_only_in_water || pond_map.kind_at(position).is_water();
+
+        // The prey with the best preference for its distance,
+        // (position, distance squared, entity, stage, score)
+        let prey_preferences = boid_tuning.prey_preferences(threat.kind);
+        let mut best_prey: Option<(Vec2, f32, Entity, life_cycles::LifeStage, f32)> = None;
+        let mut consider_prey =
+            |entity: Entity, position: Vec2, distance_2: f32, stage: life_cycles::LifeStage| {
+                let weight = prey_preferences.weight(stage);
+                if weight <= 0.0 || !in_hunting_range(position) {
+                    return;
+                }
+                let score = weight / distance_2.sqrt().max(1.0);
+                if best_prey.is_none_or(|(_, _, _, _, best_score)| score > best_score) {
+                    best_prey = Some((position, distance_2, entity, stage, score));
+                }
+            };
+
+        spatial_grid.for_each_in_radius(
+            threat_position,
+            profile.visibility_radius,
+            |duck_entity, duck_position, distance_2| {
+                let (is_duckling, is_juvenile) = if let Ok(duck_stage) = duck_query.get(duck_entity)
+                {
+                    duck_stage
+                } else {
+                    return;
+                };
+                let stage = if is_duckling {
+                    life_cycles::LifeStage::Duckling
+                } else if is_juvenile {
+                    life_cycles::LifeStage::Juvenile
+                } else {
+                    life_cycles::LifeStage::Adult
+                };
+                consider_prey(duck_entity, duck_position, distance_2, stage);
+            },
+        );
 
-        if closest_duck.is_some() && threat.eating_cooldown <= 0.0 {
-            if closest_duck.unwrap().1 <= THREAT_EATING_RADIUS_2 {
-                let mut despawned: bool = false;
-                if let Some(mut entity_cmd) = commands.get_entity(closest_duck.unwrap().2) {
-                    entity_cmd.despawn();
-                    audio::play_duck_eaten(&loaded_assets, &mut commands, &active_audio_sources);
-                    despawned = true;
-                }
-
-                if despawned {
-                    vfx::spawn_duck_eaten_effect(
-                        &mut commands,
-                        &loaded_assets,
-                        closest_duck.unwrap().0,
-                        Color::srgba(1.0, 1.0, 0.8, 0.5),
+        if prey_preferences.egg > 0.0 {
+            egg_spatial_grid.for_each_in_radius(
+                threat_position,
+                profile.visibility_radius,
+                |egg_entity, egg_position, distance_2| {
+                    consider_prey(
+                        egg_entity,
+                        egg_position,
+                        distance_2,
+                        life_cycles::LifeStage::Egg,
                     );
+                },
+            );
+        }
+        let prey = best_prey
+            .map(|(position, distance_2, entity, stage, _)| (position, distance_2, entity, stage));
+
+        match threat.state {
+            ThreatState::Wander => {
+                if prey.is_some() {
+                    threat.set_state(ThreatState::Stalk, 0.0);
+                    continue;
                 }
 
-                threat.eating_cooldown = THREAT_EATING_COOLDOWN_DURATION;
-                continue;
+                if threat.state_timer <= 0.0 {
+                    let angle = rng.gen::<f32>() * std::f32::consts::TAU;
+                    threat.wander_direction = Vec2::from_angle(angle);
+                    threat.state_timer = WANDER_TURN_INTERVAL;
+                }
+
+                if movement::is_avoiding_edge(threat_position, &world_bounds, &boid_tuning) {
+                    continue;
+                }
+                steer_towards(
+                    &mut threat_velocity.0,
+                    threat.wander_direction * profile.wander_speed,
+                    WANDER_ACCELERATION,
+                    delta_seconds,
+                );
             }
+            ThreatState::Stalk => {
+                let (prey_position, distance_2, _, _) = if let Some(prey) = prey {
+                    prey
+                } else {
+                    threat.set_state(ThreatState::Wander, 0.0);
+                    continue;
+                };
 
-            let direction =
-                (closest_duck.unwrap().0 - threat_transform.translation.xy()).normalize();
-            threat_velocity.0 += direction * TOWARDS_CLOSEST_DUCK_FACTOR * time.delta_seconds();
-        } else {
-            let window = if let Ok(window) = window_query.get_single() {
-                window
-            } else {
-                continue;
-            };
+                if distance_2 <= profile.pounce_radius * profile.pounce_radius {
+                    threat.set_state(ThreatState::Pounce, profile.pounce_duration);
+                    continue;
+                }
+
+                let direction = (prey_position - threat_position).normalize_or_zero();
+                steer_towards(
+                    &mut threat_velocity.0,
+                    direction * profile.stalk_speed,
+                    boid_tuning.towards_closest_duck_factor,
+                    delta_seconds,
+                );
+            }
+            ThreatState::Pounce => {
+                let mut ate = false;
+                if let Some((prey_position, distance_2, prey_entity, prey_stage)) = prey {
+                    if distance_2 <= THREAT_EATING_RADIUS_2 {
+                        if let Some(mut entity_cmd) = commands.get_entity(prey_entity) {
+                            entity_cmd.despawn();
+                            if prey_stage == life_cycles::LifeStage::Egg {
+                                egg_eaten_events.send(EggEaten {
+                                    position: prey_position,
+                                });
+                            } else {
+                                duck_eaten_events.send(DuckEaten {
+                                    position: prey_position,
+                                });
+                            }
+                        }
+                        ate = true;
+                    } else {
+                        let direction = (prey_position - threat_position).normalize_or_zero();
+                        steer_towards(
+                            &mut threat_velocity.0,
+                            direction * profile.pounce_speed,
+                            boid_tuning.towards_closest_duck_factor * 2.0,
+                            delta_seconds,
+                        );
+                    }
+                }
+
+                if !ate && threat.state_timer > 0.0 {
+                    continue;
+                }
+                if ate {
+                    threat.meals += 1;
+                }
+                if threat.meals >= profile.meals_before_leaving {
+                    threat.set_state(ThreatState::Leave, 0.0);
+                } else if profile.flees_after_pounce {
+                    // Keeps going in the direction of the dive
+                    threat.flee(threat_position - threat_velocity.0);
+                } else if ate {
+                    threat.set_state(ThreatState::Rest, profile.rest_duration);
+                } else {
+                    threat.set_state(ThreatState::Rest, MISSED_POUNCE_REST_DURATION);
+                }
+            }
+            ThreatState::Flee => {
+                if threat.state_timer <= 0.0 {
+                    threat.set_state(ThreatState::Wander, 0.0);
+                    continue;
+                }
+
+                let direction = (threat_position - threat.flee_from).normalize_or_zero();
+                steer_towards(
+                    &mut threat_velocity.0,
+                    direction * FLEE_SPEED,
+                    boid_tuning.towards_closest_duck_factor * 2.0,
+                    delta_seconds,
+                );
+            }
+            ThreatState::Leave => {
+                // Pikes dive away, they'd never get across the land
+                if threat.kind == ThreatKind::Pike {
+                    commands.entity(threat_entity).despawn();
+                    continue;
+                }
+
+                let outside_world = (world_bounds.size * 0.5 + Vec2::splat(LEAVE_MARGIN))
+                    .cmplt(threat_position.abs())
+                    .any();
+                if outside_world {
+                    commands.entity(threat_entity).despawn();
+                    continue;
+                }
 
-            if movement::is_avoiding_edge(threat_transform.translation.xy(), window) {
-                continue;
+                // Straight out from the middle of the pond
+                let direction = threat_position.try_normalize().unwrap_or(Vec2::X);
+                steer_towards(
+                    &mut threat_velocity.0,
+                    direction * FLEE_SPEED,
+                    boid_tuning.towards_closest_duck_factor * 2.0,
+                    delta_seconds,
+                );
             }
+            ThreatState::Rest => {
+                if threat.state_timer <= 0.0 {
+                    threat.set_state(ThreatState::Wander, 0.0);
+                    continue;
+                }
 
-            let length = threat_velocity.0.length();
-            if length > 0.0 {
-                let new_length = (length - DECELERATION_FACTOR * time.delta_seconds()).max(0.0);
-                threat_velocity.0 = threat_velocity.0 / length * new_length;
+                steer_towards(
+                    &mut threat_velocity.0,
+                    Vec2::ZERO,
+                    DECELERATION_FACTOR,
+                    delta_seconds,
+                );
             }
         }
     }
@@ -85,19 +514,16 @@ pub fn system_boid_towards_closest_duck(
 pub fn system_update_threat_animation(
     mut query: Query<(
         &mut TextureAtlas,
-        &movement::Velocity,
         &mut sprite_animation::AnimationIndices,
         &mut sprite_animation::AnimationTimer,
         &mut Threat,
     )>,
     loaded_assets: Res<LoadedAssets>,
 ) {
-    for (mut texture_atlas, velocity, mut animation_indices, mut animation_timer, mut threat) in
+    for (mut texture_atlas, mut animation_indices, mut animation_timer, mut threat) in
         query.iter_mut()
     {
-        let speed = velocity.0.length();
-
-        if speed > 100.0 {
+        if threat.state.is_running() {
             if !threat.running {
                 threat.running = true;
                 texture_atlas.layout = loaded_assets.threat_running_atlas.clone();
@@ -126,5 +552,8 @@ pub fn system_update_threat_animation(
             *animation_timer =
                 sprite_animation::AnimationTimer(Timer::from_seconds(1.5, TimerMode::Repeating));
         }
+
+        // Resting threats hold still
+        animation_indices.paused = threat.state == ThreatState::Rest;
     }
 }
@@ -0,0 +1,172 @@
+use crate::boid::BoidProfile;
+use crate::simulation::LoadedConfigs;
+use crate::threat_boid::{PreyPreferences, ThreatKind};
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoadFailedEvent, AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub const BOID_TUNING_PATH: &str = "config/boid.tuning.ron";
+
+// Steering weights shared by all flocking systems, loaded from `BOID_TUNING_PATH`
+#[derive(Asset, TypePath, Resource, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct BoidTuning {
+    pub duckling_profile: BoidProfile,
+    pub juvenile_profile: BoidProfile,
+    pub adult_profile: BoidProfile,
+
+    pub towards_food_factor: f32,
+    pub avoid_threats_factor: f32,
+    pub mob_threats_factor: f32,
+
+    pub towards_closest_duck_factor: f32,
+    pub cat_prey: PreyPreferences,
+    pub hawk_prey: PreyPreferences,
+    pub pike_prey: PreyPreferences,
+    pub fox_prey: PreyPreferences,
+
+    pub edge_margin: f32,
+    pub turn_factor: f32,
+    pub avoid_obstacles_factor: f32,
+}
+
+impl Default for BoidTuning {
+    fn default() -> Self {
+        Self {
+            duckling_profile: BoidProfile {
+                protected_radius: 45.0,
+                avoid_factor: 0.35,
+                visibility_radius: 120.0,
+                align_factor: 0.5,
+                cohesion_factor: 0.3,
+            },
+            juvenile_profile: BoidProfile {
+                protected_radius: 70.0,
+                avoid_factor: 0.35,
+                visibility_radius: 150.0,
+                align_factor: 0.5,
+                cohesion_factor: 0.15,
+            },
+            adult_profile: BoidProfile {
+                protected_radius: 80.0,
+                avoid_factor: 0.35,
+                visibility_radius: 200.0,
+                align_factor: 0.4,
+                cohesion_factor: 0.1,
+            },
+
+            towards_food_factor: 120.0,
+            avoid_threats_factor: 100.0,
+            mob_threats_factor: 250.0,
+
+            towards_closest_duck_factor: 300.0,
+            cat_prey: PreyPreferences {
+                egg: 0.5,
+                duckling: 1.5,
+                juvenile: 1.0,
+                adult: 1.0,
+            },
+            hawk_prey: PreyPreferences {
+                egg: 0.0,
+                duckling: 2.0,
+                juvenile: 1.5,
+                adult: 0.5,
+            },
+            pike_prey: PreyPreferences {
+                egg: 0.0,
+                duckling: 2.0,
+                juvenile: 1.0,
+                adult: 1.0,
+            },
+            fox_prey: PreyPreferences {
+                egg: 4.0,
+                duckling: 2.0,
+                juvenile: 1.0,
+                adult: 0.5,
+            },
+
+            edge_margin: 100.0,
+            turn_factor: 130.0,
+            avoid_obstacles_factor: 400.0,
+        }
+    }
+}
+
+impl BoidTuning {
+    pub fn prey_preferences(&self, kind: ThreatKind) -> &PreyPreferences {
+        match kind {
+            ThreatKind::Cat => &self.cat_prey,
+            ThreatKind::Hawk => &self.hawk_prey,
+            ThreatKind::Pike => &self.pike_prey,
+            ThreatKind::Fox => &self.fox_prey,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct BoidTuningLoader;
+
+impl AssetLoader for BoidTuningLoader {
+    type Asset = BoidTuning;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes::<BoidTuning>(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tuning.ron"]
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct BoidTuningHandle(pub Handle<BoidTuning>);
+
+pub fn system_load_boid_tuning(
+    asset_server: Res<AssetServer>,
+    mut boid_tuning_handle: ResMut<BoidTuningHandle>,
+) {
+    boid_tuning_handle.0 = asset_server.load(BOID_TUNING_PATH);
+}
+
+// Copies the asset into the `BoidTuning` resource whenever it is (re)loaded
+pub fn system_apply_boid_tuning(
+    mut asset_events: EventReader<AssetEvent<BoidTuning>>,
+    mut load_failed_events: EventReader<AssetLoadFailedEvent<BoidTuning>>,
+    boid_tuning_assets: Res<Assets<BoidTuning>>,
+    boid_tuning_handle: Res<BoidTuningHandle>,
+    mut boid_tuning: ResMut<BoidTuning>,
+    mut loaded_configs: ResMut<LoadedConfigs>,
+) {
+    for event in load_failed_events.read() {
+        warn!(
+            "Could not load {}, using default tuning: {}",
+            event.path, event.error
+        );
+        loaded_configs.boid_tuning = true;
+    }
+
+    for event in asset_events.read() {
+        match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => {
+                if *id != boid_tuning_handle.0.id() {
+                    continue;
+                }
+                if let Some(loaded_tuning) = boid_tuning_assets.get(*id) {
+                    *boid_tuning = loaded_tuning.clone();
+                    loaded_configs.boid_tuning = true;
+                }
+            }
+            _ => {}
+        }
+    }
+}
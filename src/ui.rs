@@ -1,8 +1,12 @@
-use crate::leaderboard::LeaderboardMarker;
+use crate::difficulty::{CurrentDifficulty, CustomSetting, Difficulty, DifficultyPresets};
+use crate::leaderboard::{Leaderboard, LeaderboardBackend, LeaderboardMarker, LeaderboardTitle};
+use crate::lineage::FamilyTree;
 use crate::player::PlayerStats;
-use crate::{audio, game_state, spawning};
+use crate::rng::SimulationRng;
+use crate::save_game::ContinueSavedGame;
+use crate::simulation::LoadedConfigs;
+use crate::{audio, game_state, leaderboard, replay, save_game, spawning};
 use bevy::prelude::*;
-use bevy_jornet::Leaderboard;
 
 const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
 pub const HOVERED_BUTTON: Color = Color::srgb(0.25, 0.25, 0.25);
@@ -18,6 +22,11 @@ const BACKGROUND_COLOR: Color = Color::srgba(0.1, 0.1, 0.1, 0.80);
 #[derive(Component)]
 pub enum UIButtonAction {
     Play,
+    // Cycles through the difficulties
+    Difficulty,
+    // Steps a setting of the custom difficulty down (-1.0) or up (1.0)
+    AdjustCustomSetting(CustomSetting, f32),
+    Continue,
     ContinueSimulation,
     Restart,
 }
@@ -25,11 +34,31 @@ pub enum UIButtonAction {
 #[derive(Component)]
 pub struct OnMenuScreen;
 
+// The name on the difficulty selector button
+#[derive(Component)]
+pub struct DifficultyText;
+
+// The custom difficulty controls, only shown while `Difficulty::Custom` is selected
+#[derive(Component)]
+pub struct CustomDifficultyPanel;
+
+// The value next to the - and + buttons of a custom difficulty setting
+#[derive(Component)]
+pub struct CustomSettingText(pub CustomSetting);
+
+// Filled in by `inspector::system_update_inspector_ui`
+#[derive(Component)]
+pub struct DuckInspectorText;
+
 #[derive(Component, PartialEq)]
 pub enum GameStatusWidgets {
     RemainingTime,
     DucklingsBorn,
     DucksAlive,
+    SelectedFood,
+    Scare,
+    FollowFlock,
+    ThreatWave,
 }
 
 pub fn system_ui_actions(
@@ -37,17 +66,44 @@ pub fn system_ui_actions(
     main_menu_screen: Query<Entity, With<OnMenuScreen>>,
     mut player_stats: ResMut<PlayerStats>,
     mut game_state: ResMut<NextState<game_state::GameState>>,
+    mut continue_saved_game: EventWriter<ContinueSavedGame>,
+    mut current_difficulty: ResMut<CurrentDifficulty>,
+    mut difficulty_presets: ResMut<DifficultyPresets>,
+    mut difficulty_text_query: Query<&mut Text, With<DifficultyText>>,
     mut commands: Commands,
     loaded_assets: Res<spawning::LoadedAssets>,
+    loaded_configs: Res<LoadedConfigs>,
 ) {
     for (interaction, ui_button_action) in &interaction_query {
         if *interaction == Interaction::Pressed {
             match ui_button_action {
+                // Nothing is spawned before the configs and the pond map are loaded
+                UIButtonAction::Play | UIButtonAction::Continue
+                    if !loaded_configs.is_all_loaded() => {}
                 UIButtonAction::Play => {
                     audio::play_button_clicked(&loaded_assets, &mut commands);
                     game_state.set(game_state::GameState::Running);
                     despawn_screen::<OnMenuScreen>(&main_menu_screen, &mut commands);
                 }
+                UIButtonAction::Difficulty => {
+                    audio::play_button_clicked(&loaded_assets, &mut commands);
+                    let difficulty = current_difficulty.difficulty.next();
+                    current_difficulty.select(difficulty, &difficulty_presets);
+                    for mut text in difficulty_text_query.iter_mut() {
+                        text.sections[0].value = difficulty.name().to_string();
+                    }
+                }
+                UIButtonAction::AdjustCustomSetting(setting, steps) => {
+                    audio::play_button_clicked(&loaded_assets, &mut commands);
+                    setting.adjust(&mut difficulty_presets.custom, *steps);
+                    current_difficulty.select(Difficulty::Custom, &difficulty_presets);
+                }
+                UIButtonAction::Continue => {
+                    audio::play_button_clicked(&loaded_assets, &mut commands);
+                    continue_saved_game.send(ContinueSavedGame);
+                    game_state.set(game_state::GameState::Running);
+                    despawn_screen::<OnMenuScreen>(&main_menu_screen, &mut commands);
+                }
                 UIButtonAction::ContinueSimulation => {
                     player_stats.is_simulating = true;
                     audio::play_button_clicked(&loaded_assets, &mut commands);
@@ -79,6 +135,28 @@ pub fn system_button_color(
     }
 }
 
+// Shows the custom difficulty controls for `Difficulty::Custom`, with its current values
+pub fn system_update_custom_difficulty_ui(
+    current_difficulty: Res<CurrentDifficulty>,
+    mut panel_query: Query<&mut Style, With<CustomDifficultyPanel>>,
+    mut setting_text_query: Query<(&mut Text, &CustomSettingText)>,
+) {
+    if !current_difficulty.is_changed() {
+        return;
+    }
+
+    for mut style in panel_query.iter_mut() {
+        style.display = if current_difficulty.difficulty == Difficulty::Custom {
+            Display::Flex
+        } else {
+            Display::None
+        };
+    }
+    for (mut text, setting_text) in setting_text_query.iter_mut() {
+        text.sections[0].value = setting_text.0.format(&current_difficulty.settings);
+    }
+}
+
 fn despawn_screen<T: Component>(to_despawn: &Query<Entity, With<T>>, commands: &mut Commands) {
     for entity in to_despawn {
         commands.entity(entity).despawn_recursive();
@@ -89,6 +167,9 @@ pub fn system_create_main_menu(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     loaded_assets: Res<spawning::LoadedAssets>,
+    current_difficulty: Res<CurrentDifficulty>,
+    recorder: Option<Res<replay::Recorder>>,
+    replay: Option<Res<replay::Replay>>,
 ) {
     // Common style for all buttons on the screen
     let button_style = Style {
@@ -174,6 +255,69 @@ pub fn system_create_main_menu(
                             parent
                                 .spawn(TextBundle::from_section("Play", button_text_style.clone()));
                         });
+
+                    parent.spawn(TextBundle::from_section(
+                        "Difficulty",
+                        TextStyle {
+                            font_size: 20.0,
+                            color: TEXT_COLOR,
+                            ..default()
+                        },
+                    ));
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    margin: UiRect::new(
+                                        Val::Px(20.0),
+                                        Val::Px(20.0),
+                                        Val::Px(5.0),
+                                        Val::Px(20.0),
+                                    ),
+                                    ..button_style.clone()
+                                },
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            UIButtonAction::Difficulty,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn((
+                                TextBundle::from_section(
+                                    current_difficulty.difficulty.name(),
+                                    button_text_style.clone(),
+                                ),
+                                DifficultyText,
+                            ));
+                        });
+
+                    spawn_custom_difficulty_panel(parent, &current_difficulty);
+
+                    // Only offered when a previous round was left unfinished. Recordings only hold
+                    // the seed and the inputs, so a continued round could not be replayed.
+                    if save_game::has_save_game() && recorder.is_none() && replay.is_none() {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: button_style.clone(),
+                                    background_color: NORMAL_BUTTON.into(),
+                                    ..default()
+                                },
+                                UIButtonAction::Continue,
+                            ))
+                            .with_children(|parent| {
+                                let icon = asset_server.load("ui/right.png");
+                                parent.spawn(ImageBundle {
+                                    style: button_icon_style.clone(),
+                                    image: UiImage::new(icon),
+                                    ..default()
+                                });
+                                parent.spawn(TextBundle::from_section(
+                                    "Continue",
+                                    button_text_style.clone(),
+                                ));
+                            });
+                    }
                 });
         });
 }
@@ -183,8 +327,12 @@ pub fn system_create_time_over_menu(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     player_stats: Res<PlayerStats>,
-    leaderboard: ResMut<Leaderboard>,
+    mut leaderboard: ResMut<Leaderboard>,
     loaded_assets: Res<spawning::LoadedAssets>,
+    rng: Res<SimulationRng>,
+    current_difficulty: Res<CurrentDifficulty>,
+    family_tree: Res<FamilyTree>,
+    replay: Option<Res<replay::Replay>>,
 ) {
     // Common style for all buttons on the screen
     let button_style = Style {
@@ -252,8 +400,10 @@ pub fn system_create_time_over_menu(
 
                     let player_score = player_stats.score;
 
-                    if leaderboard.get_player().is_some() {
-                        leaderboard.send_score(player_score as f32);
+                    // A replayed round was played by whoever recorded it, not by the viewer
+                    if leaderboard.player_name().is_some() && replay.is_none() {
+                        leaderboard
+                            .submit_score(player_score as f32, current_difficulty.difficulty);
                     }
 
                     let score_string: String =
@@ -268,11 +418,14 @@ pub fn system_create_time_over_menu(
                             },
                         )
                         .with_style(Style {
-                            margin: UiRect::bottom(Val::Px(50.0)),
+                            margin: UiRect::bottom(Val::Px(10.0)),
                             ..default()
                         }),
                     );
 
+                    spawn_lineage_text(parent, &family_tree);
+                    spawn_seed_text(parent, rng.seed(), 40.0);
+
                     parent
                         .spawn((
                             ButtonBundle {
@@ -325,6 +478,8 @@ pub fn system_create_game_over_menu(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     loaded_assets: Res<spawning::LoadedAssets>,
+    rng: Res<SimulationRng>,
+    family_tree: Res<FamilyTree>,
 ) {
     // Common style for all buttons on the screen
     let button_style = Style {
@@ -400,11 +555,14 @@ pub fn system_create_game_over_menu(
                             },
                         )
                         .with_style(Style {
-                            margin: UiRect::bottom(Val::Px(90.0)),
+                            margin: UiRect::bottom(Val::Px(10.0)),
                             ..default()
                         }),
                     );
 
+                    spawn_lineage_text(parent, &family_tree);
+                    spawn_seed_text(parent, rng.seed(), 80.0);
+
                     parent
                         .spawn((
                             ButtonBundle {
@@ -430,6 +588,143 @@ pub fn system_create_game_over_menu(
         });
 }
 
+fn spawn_custom_difficulty_panel(
+    parent: &mut ChildBuilder,
+    current_difficulty: &CurrentDifficulty,
+) {
+    let label_style = TextStyle {
+        font_size: 20.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+    let step_button_style = Style {
+        width: Val::Px(30.0),
+        height: Val::Px(30.0),
+        margin: UiRect::horizontal(Val::Px(5.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+
+    parent
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    margin: UiRect::bottom(Val::Px(20.0)),
+                    display: if current_difficulty.difficulty == Difficulty::Custom {
+                        Display::Flex
+                    } else {
+                        Display::None
+                    },
+                    ..default()
+                },
+                ..default()
+            },
+            CustomDifficultyPanel,
+        ))
+        .with_children(|parent| {
+            for setting in CustomSetting::ALL {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            margin: UiRect::vertical(Val::Px(2.0)),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(
+                            TextBundle::from_section(setting.name(), label_style.clone())
+                                .with_style(Style {
+                                    width: Val::Px(150.0),
+                                    ..default()
+                                }),
+                        );
+                        for (label, steps) in [("-", -1.0), ("+", 1.0)] {
+                            if steps > 0.0 {
+                                parent.spawn((
+                                    TextBundle::from_section(
+                                        setting.format(&current_difficulty.settings),
+                                        label_style.clone(),
+                                    )
+                                    .with_text_justify(JustifyText::Center)
+                                    .with_style(Style {
+                                        width: Val::Px(60.0),
+                                        ..default()
+                                    }),
+                                    CustomSettingText(setting),
+                                ));
+                            }
+                            parent
+                                .spawn((
+                                    ButtonBundle {
+                                        style: step_button_style.clone(),
+                                        background_color: NORMAL_BUTTON.into(),
+                                        ..default()
+                                    },
+                                    UIButtonAction::AdjustCustomSetting(setting, steps),
+                                ))
+                                .with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section(
+                                        label,
+                                        label_style.clone(),
+                                    ));
+                                });
+                        }
+                    });
+            }
+        });
+}
+
+fn spawn_lineage_text(parent: &mut ChildBuilder, family_tree: &FamilyTree) {
+    let mut lineage_string = format!(
+        "Generation Reached: {}\nDynasties: {}",
+        family_tree.generation_reached(),
+        family_tree.dynasties()
+    );
+    if let Some((_, record)) = family_tree.most_prolific() {
+        lineage_string += &format!(
+            "\nMost Prolific Duck: {} eggs (generation {})",
+            record.offspring, record.generation
+        );
+    }
+    parent.spawn(
+        TextBundle::from_section(
+            lineage_string,
+            TextStyle {
+                font_size: 20.0,
+                color: TEXT_COLOR,
+                ..default()
+            },
+        )
+        .with_text_justify(JustifyText::Center)
+        .with_style(Style {
+            margin: UiRect::bottom(Val::Px(10.0)),
+            ..default()
+        }),
+    );
+}
+
+// Shows the seed of the finished run, which can be passed back in with `--seed`
+fn spawn_seed_text(parent: &mut ChildBuilder, seed: u64, bottom_margin: f32) {
+    parent.spawn(
+        TextBundle::from_section(
+            format!("Seed: {seed}"),
+            TextStyle {
+                font_size: 15.0,
+                color: Color::srgb(0.45, 0.45, 0.45),
+                ..default()
+            },
+        )
+        .with_style(Style {
+            margin: UiRect::bottom(Val::Px(bottom_margin)),
+            ..default()
+        }),
+    );
+}
+
 pub fn system_spawn_leaderboard_ui(
     mut commands: Commands,
     loaded_assets: Res<spawning::LoadedAssets>,
@@ -460,9 +755,9 @@ pub fn system_spawn_leaderboard_ui(
                     ..default()
                 })
                 .with_children(|parent02| {
-                    parent02.spawn(
+                    parent02.spawn((
                         TextBundle::from_section(
-                            "Leaderboard  (Global)",
+                            leaderboard::leaderboard_title(Difficulty::default()),
                             TextStyle {
                                 font: loaded_assets.pixel_font_handle.clone(),
                                 font_size: 16.0,
@@ -473,7 +768,8 @@ pub fn system_spawn_leaderboard_ui(
                             margin: UiRect::px(10.0, 0.0, 5.0, 0.0),
                             ..default()
                         }),
-                    );
+                        LeaderboardTitle,
+                    ));
 
                     parent02
                         .spawn(NodeBundle {
@@ -608,11 +904,120 @@ pub fn system_spawn_leaderboard_ui(
                             },
                         )
                         .with_style(Style {
-                            margin: UiRect::px(10.0, 10.0, 5.0, 4.0),
+                            margin: UiRect::px(10.0, 10.0, 5.0, 0.0),
                             ..default()
                         }),
                         GameStatusWidgets::DucksAlive,
                     ));
+
+                    parent02.spawn((
+                        TextBundle::from_section(
+                            "Food [1-5]: Bread",
+                            TextStyle {
+                                font_size: 15.0,
+                                color: TEXT_COLOR,
+                                ..default()
+                            },
+                        )
+                        .with_style(Style {
+                            margin: UiRect::px(10.0, 10.0, 5.0, 0.0),
+                            ..default()
+                        }),
+                        GameStatusWidgets::SelectedFood,
+                    ));
+
+                    parent02.spawn((
+                        TextBundle::from_section(
+                            "Scare [Right Click / Space]: Ready",
+                            TextStyle {
+                                font_size: 15.0,
+                                color: TEXT_COLOR,
+                                ..default()
+                            },
+                        )
+                        .with_style(Style {
+                            margin: UiRect::px(10.0, 10.0, 5.0, 0.0),
+                            ..default()
+                        }),
+                        GameStatusWidgets::Scare,
+                    ));
+
+                    parent02.spawn((
+                        TextBundle::from_section(
+                            "Follow Flock [F]: On",
+                            TextStyle {
+                                font_size: 15.0,
+                                color: TEXT_COLOR,
+                                ..default()
+                            },
+                        )
+                        .with_style(Style {
+                            margin: UiRect::px(10.0, 10.0, 5.0, 0.0),
+                            ..default()
+                        }),
+                        GameStatusWidgets::FollowFlock,
+                    ));
+
+                    parent02.spawn((
+                        TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                font_size: 15.0,
+                                color: YELLOW_TEXT,
+                                ..default()
+                            },
+                        )
+                        .with_style(Style {
+                            margin: UiRect::px(10.0, 10.0, 5.0, 4.0),
+                            ..default()
+                        }),
+                        GameStatusWidgets::ThreatWave,
+                    ));
+                });
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        margin: UiRect::new(Val::Px(15.0), Val::Auto, Val::Px(10.0), Val::Auto),
+                        justify_content: JustifyContent::Start,
+                        align_items: AlignItems::Start,
+                        flex_direction: FlexDirection::Column,
+                        ..default()
+                    },
+                    background_color: BACKGROUND_COLOR.into(),
+                    ..default()
+                })
+                .with_children(|parent02| {
+                    parent02.spawn(
+                        TextBundle::from_section(
+                            "Duck Inspector",
+                            TextStyle {
+                                font: loaded_assets.pixel_font_handle.clone(),
+                                font_size: 16.0,
+                                color: TEXT_COLOR,
+                            },
+                        )
+                        .with_style(Style {
+                            margin: UiRect::px(10.0, 10.0, 5.0, 5.0),
+                            ..default()
+                        }),
+                    );
+
+                    parent02.spawn((
+                        TextBundle::from_section(
+                            "Shift + Click a duck to inspect it",
+                            TextStyle {
+                                font_size: 15.0,
+                                color: TEXT_COLOR,
+                                ..default()
+                            },
+                        )
+                        .with_style(Style {
+                            margin: UiRect::px(10.0, 10.0, 5.0, 4.0),
+                            ..default()
+                        }),
+                        DuckInspectorText,
+                    ));
                 });
         });
 }
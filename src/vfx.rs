@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 //use bevy::window::PrimaryWindow;
-use crate::spawning;
+use crate::{director, duck_boid, food, hunger, life_cycles, scare, spawning, threat_boid};
 use bevy_particle_systems::{
     ParticleBurst, ParticleSystem, ParticleSystemBundle, Playing, VelocityModifier,
 };
@@ -25,6 +25,62 @@ pub fn spawn_particle_systems(
 }
 */
 
+pub fn system_spawn_gameplay_effects(
+    mut commands: Commands,
+    loaded_assets: Res<spawning::LoadedAssets>,
+    mut duck_eaten_events: EventReader<threat_boid::DuckEaten>,
+    mut egg_eaten_events: EventReader<threat_boid::EggEaten>,
+    mut food_eaten_events: EventReader<duck_boid::FoodEaten>,
+    mut egg_hatched_events: EventReader<life_cycles::EggHatched>,
+    mut transition_events: EventReader<life_cycles::LifeCycleTransitioned>,
+    mut duck_starved_events: EventReader<hunger::DuckStarved>,
+    mut died_of_old_age_events: EventReader<life_cycles::DuckDiedOfOldAge>,
+    mut food_rotted_events: EventReader<food::FoodRotted>,
+    mut threats_scared_events: EventReader<scare::ThreatsScared>,
+    mut wave_warning_events: EventReader<director::ThreatWaveWarning>,
+) {
+    for event in duck_eaten_events.read() {
+        spawn_duck_eaten_effect(
+            &mut commands,
+            &loaded_assets,
+            event.position,
+            Color::srgba(1.0, 1.0, 0.8, 0.5),
+        );
+    }
+    for event in egg_eaten_events.read() {
+        spawn_egg_eaten_effect(&mut commands, &loaded_assets, event.position);
+    }
+    for event in food_eaten_events.read() {
+        spawn_food_eaten_effect(&mut commands, &loaded_assets, event.position);
+    }
+    for event in egg_hatched_events.read() {
+        spawn_egg_hatched_effect(&mut commands, &loaded_assets, event.position);
+    }
+    for event in transition_events.read() {
+        spawn_duck_cycle_transition_effect(&mut commands, &loaded_assets, event.position);
+    }
+    for event in duck_starved_events.read() {
+        spawn_duck_eaten_effect(
+            &mut commands,
+            &loaded_assets,
+            event.position,
+            Color::srgba(0.6, 0.6, 0.6, 0.5),
+        );
+    }
+    for event in died_of_old_age_events.read() {
+        spawn_old_age_death_effect(&mut commands, &loaded_assets, event.position);
+    }
+    for event in food_rotted_events.read() {
+        spawn_food_rotted_effect(&mut commands, &loaded_assets, event.position);
+    }
+    for event in threats_scared_events.read() {
+        spawn_splash_effect(&mut commands, &loaded_assets, event.position);
+    }
+    for event in wave_warning_events.read() {
+        spawn_wave_warning_effect(&mut commands, &loaded_assets, event.position);
+    }
+}
+
 pub fn spawn_duck_eaten_effect(
     commands: &mut Commands,
     loaded_assets: &Res<spawning::LoadedAssets>,
@@ -64,6 +120,85 @@ pub fn spawn_duck_eaten_effect(
     ));
 }
 
+// Bits of shell instead of feathers
+pub fn spawn_egg_eaten_effect(
+    commands: &mut Commands,
+    loaded_assets: &Res<spawning::LoadedAssets>,
+    world_position: Vec2,
+) {
+    let start_color = Color::srgba(1.0, 0.97, 0.85, 0.8);
+    let end_color = start_color.with_alpha(0.0);
+    let position = Transform::from_translation(world_position.extend(5.0));
+
+    commands.spawn((
+        ParticleSystemBundle {
+            transform: position,
+            global_transform: GlobalTransform::from_translation(position.translation),
+            particle_system: ParticleSystem {
+                texture: loaded_assets.circle_image.clone().into(),
+                spawn_rate_per_second: 0.0.into(),
+                max_particles: 12,
+                initial_speed: (20.0..90.0).into(),
+                initial_rotation: (0.0..360.0_f32.to_radians()).into(),
+                lifetime: (0.2..0.35).into(),
+                scale: (0.4..0.2).into(),
+                velocity_modifiers: vec![
+                    VelocityModifier::Drag(0.001.into()),
+                    VelocityModifier::Vector(Vec3::new(0.0, -120.0, 0.0).into()),
+                ],
+                color: (start_color..end_color).into(),
+                bursts: vec![ParticleBurst {
+                    time: 0.0,
+                    count: 12,
+                }],
+                ..ParticleSystem::oneshot()
+            },
+            ..default()
+        },
+        Playing,
+    ));
+}
+
+// Feathers slowly drifting up instead of bursting apart
+pub fn spawn_old_age_death_effect(
+    commands: &mut Commands,
+    loaded_assets: &Res<spawning::LoadedAssets>,
+    world_position: Vec2,
+) {
+    let start_color = Color::srgba(0.9, 0.9, 1.0, 0.6);
+    let end_color = start_color.with_alpha(0.0);
+    let position = Transform::from_translation(world_position.extend(5.0));
+
+    commands.spawn((
+        ParticleSystemBundle {
+            transform: position,
+            global_transform: GlobalTransform::from_translation(position.translation),
+            particle_system: ParticleSystem {
+                texture: loaded_assets.feather_image.clone().into(),
+                spawn_rate_per_second: 0.0.into(),
+                max_particles: 15,
+                initial_speed: (5.0..40.0).into(),
+                initial_rotation: (0.0..360.0_f32.to_radians()).into(),
+                rotation_speed: (-2.0..2.0).into(),
+                lifetime: (1.0..1.5).into(),
+                scale: (0.1..0.05).into(),
+                velocity_modifiers: vec![
+                    VelocityModifier::Drag(0.001.into()),
+                    VelocityModifier::Vector(Vec3::new(0.0, 60.0, 0.0).into()),
+                ],
+                color: (start_color..end_color).into(),
+                bursts: vec![ParticleBurst {
+                    time: 0.0,
+                    count: 15,
+                }],
+                ..ParticleSystem::oneshot()
+            },
+            ..default()
+        },
+        Playing,
+    ));
+}
+
 pub fn spawn_duck_cycle_transition_effect(
     commands: &mut Commands,
     loaded_assets: &Res<spawning::LoadedAssets>,
@@ -140,6 +275,45 @@ pub fn spawn_food_eaten_effect(
     ));
 }
 
+// A small cloud that fades out where rotten food was
+pub fn spawn_food_rotted_effect(
+    commands: &mut Commands,
+    loaded_assets: &Res<spawning::LoadedAssets>,
+    world_position: Vec2,
+) {
+    let start_color: Color = Color::srgba(0.55, 0.65, 0.35, 0.4);
+    let end_color = start_color.with_alpha(0.0);
+    let position = Transform::from_translation(world_position.extend(-1.0));
+
+    commands.spawn((
+        ParticleSystemBundle {
+            transform: position,
+            global_transform: GlobalTransform::from_translation(position.translation),
+            particle_system: ParticleSystem {
+                texture: loaded_assets.circle_image.clone().into(),
+                spawn_rate_per_second: 0.0.into(),
+                max_particles: 12,
+                initial_speed: (5.0..30.0).into(),
+                initial_rotation: (0.0..360.0_f32.to_radians()).into(),
+                lifetime: (0.8..1.2).into(),
+                scale: (0.4..0.8).into(),
+                velocity_modifiers: vec![
+                    VelocityModifier::Drag(0.001.into()),
+                    VelocityModifier::Vector(Vec3::new(0.0, 20.0, 0.0).into()),
+                ],
+                color: (start_color..end_color).into(),
+                bursts: vec![ParticleBurst {
+                    time: 0.0,
+                    count: 12,
+                }],
+                ..ParticleSystem::oneshot()
+            },
+            ..default()
+        },
+        Playing,
+    ));
+}
+
 pub fn spawn_egg_hatched_effect(
     commands: &mut Commands,
     loaded_assets: &Res<spawning::LoadedAssets>,
@@ -178,3 +352,86 @@ pub fn spawn_egg_hatched_effect(
         Playing,
     ));
 }
+
+// A quick ring of water drops where the player clapped
+pub fn spawn_splash_effect(
+    commands: &mut Commands,
+    loaded_assets: &Res<spawning::LoadedAssets>,
+    world_position: Vec2,
+) {
+    let start_color = Color::srgba(0.8, 0.9, 1.0, 0.6);
+    let end_color = start_color.with_alpha(0.0);
+    let position = Transform::from_translation(world_position.extend(6.0));
+
+    commands.spawn((
+        ParticleSystemBundle {
+            transform: position,
+            global_transform: GlobalTransform::from_translation(position.translation),
+            particle_system: ParticleSystem {
+                texture: loaded_assets.circle_image.clone().into(),
+                spawn_rate_per_second: 0.0.into(),
+                max_particles: 30,
+                initial_speed: (150.0..200.0).into(),
+                initial_rotation: (0.0..360.0_f32.to_radians()).into(),
+                lifetime: (0.3..0.5).into(),
+                scale: (0.8..0.4).into(),
+                velocity_modifiers: vec![VelocityModifier::Drag(0.01.into())],
+                color: (start_color..end_color).into(),
+                bursts: vec![ParticleBurst {
+                    time: 0.0,
+                    count: 30,
+                }],
+                ..ParticleSystem::oneshot()
+            },
+            ..default()
+        },
+        Playing,
+    ));
+}
+
+// Red pulses at the edge a threat wave is about to come in from
+pub fn spawn_wave_warning_effect(
+    commands: &mut Commands,
+    loaded_assets: &Res<spawning::LoadedAssets>,
+    world_position: Vec2,
+) {
+    let start_color = Color::srgba(1.0, 0.3, 0.2, 0.7);
+    let end_color = start_color.with_alpha(0.0);
+    let position = Transform::from_translation(world_position.extend(6.0));
+
+    commands.spawn((
+        ParticleSystemBundle {
+            transform: position,
+            global_transform: GlobalTransform::from_translation(position.translation),
+            particle_system: ParticleSystem {
+                texture: loaded_assets.circle_image.clone().into(),
+                spawn_rate_per_second: 0.0.into(),
+                max_particles: 60,
+                initial_speed: (40.0..80.0).into(),
+                initial_rotation: (0.0..360.0_f32.to_radians()).into(),
+                lifetime: (0.6..0.8).into(),
+                scale: (1.2..0.6).into(),
+                velocity_modifiers: vec![VelocityModifier::Drag(0.01.into())],
+                color: (start_color..end_color).into(),
+                bursts: vec![
+                    ParticleBurst {
+                        time: 0.0,
+                        count: 20,
+                    },
+                    ParticleBurst {
+                        time: 1.0,
+                        count: 20,
+                    },
+                    ParticleBurst {
+                        time: 2.0,
+                        count: 20,
+                    },
+                ],
+                system_duration_seconds: 3.0,
+                ..ParticleSystem::oneshot()
+            },
+            ..default()
+        },
+        Playing,
+    ));
+}